@@ -1,4 +1,6 @@
 rsfm.show_hidden = false
+-- "single", "dual" or "miller"
+rsfm.layout = "single"
 rsfm.entry_format = {
     {
         type = "name",
@@ -6,6 +8,13 @@ rsfm.entry_format = {
         is_fixed_width = false,
         alignment = "left",
     },
+    {
+        type = "modified",
+        width = 16,
+        is_fixed_width = true,
+        alignment = "left",
+        format = "%Y-%m-%d %H:%M",
+    },
     {
         type = "permissions",
         width = 20,
@@ -19,3 +28,48 @@ rsfm.entry_format = {
         alignment = "right",
     }
 }
+rsfm.sort = {
+    by = "name",
+    reverse = false,
+    dirs_first = true,
+    natural = true,
+    case_sensitive = false,
+}
+rsfm.preview = {
+    enabled = true,
+    width = 40,
+    max_lines = 200,
+    max_bytes = 65536,
+}
+rsfm.journal = {
+    persist = true,
+    size = 100,
+}
+-- columns of the "miller" layout, sized like the ones of entry_format
+rsfm.miller = {
+    parent = { width = 1, is_fixed_width = false },
+    current = { width = 3, is_fixed_width = false },
+    preview = { width = 4, is_fixed_width = false },
+}
+rsfm.bookmarks = {
+    d = "~/Downloads",
+    c = "~/.config",
+}
+rsfm.keys = {
+    ["gg"] = "top",
+    ["G"] = "bottom",
+    ["<C-d>"] = "page_down",
+    ["<C-u>"] = "page_up",
+    ["."] = "toggle_hidden",
+    ["!"] = "terminal",
+    ["gh"] = "home",
+}
+rsfm.commands = {
+    terminal = function()
+        rsfm.shell(os.getenv("SHELL") or "sh")
+    end,
+    home = function()
+        rsfm.cd("~")
+        rsfm.notify("Moved to " .. rsfm.cwd())
+    end,
+}
@@ -0,0 +1,1366 @@
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{KeyCode, KeyModifiers};
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::*;
+use tui::text::Span;
+use tui::widgets::*;
+
+use crate::bookmarks::Bookmarks;
+use crate::config::column::ColumnType;
+use crate::config::keys::{Action, Key, Lookup};
+use crate::config::layout;
+use crate::config::sort::SortOptions;
+use crate::config::ViewOptions;
+use crate::journal::{self, Journal};
+use crate::mime::MimeTypes;
+use crate::model::{self, details, Item};
+use crate::navigation::Navigator;
+use crate::operation::{self, Operation, Resolution};
+use crate::preview::{self, Previewer};
+use crate::script::{self, Script};
+use crate::trash::TrashedItem;
+use crate::watcher::{Changes, Watcher};
+
+mod bookmarks;
+mod filter;
+mod finder;
+mod input;
+mod miller;
+mod panes;
+mod rename;
+mod search;
+mod tabs;
+mod trash;
+
+const MAX_MESSAGE_LINES: usize = 5;
+// borders, the current file and the total
+const PROGRESS_HEIGHT: u16 = 4;
+
+/// A question shown at the bottom of the screen, answered by the next key press.
+enum Prompt {
+    Delete(Vec<PathBuf>),
+    Purge(Vec<TrashedItem>),
+    /// Checked renames, previewed in the main area.
+    Rename(Vec<(PathBuf, PathBuf)>),
+    /// The key to bookmark the current directory under.
+    SetBookmark,
+    JumpToBookmark,
+}
+
+/// What the main area shows.
+enum View {
+    Directory,
+    Trash(trash::TrashView),
+    Rename(rename::RenameDialog),
+    Search(search::SearchView),
+    Bookmarks(bookmarks::BookmarksView),
+}
+
+fn highlight_style() -> Style {
+    Style::default().fg(Color::Black).bg(Color::LightYellow)
+}
+
+/// The cursor of a listing without the focus.
+fn inactive_highlight_style() -> Style {
+    Style::default().bg(Color::DarkGray)
+}
+
+/// The new cursor position in a list of `len` entries if `action` moves the cursor.
+fn move_cursor(action: Action, index: usize, len: usize, page_height: usize) -> Option<usize> {
+    let index = match action {
+        Action::MoveUp => index.saturating_sub(1),
+        Action::MoveDown => index + 1,
+        Action::PageUp => index.saturating_sub(page_height),
+        Action::PageDown => index + page_height,
+        Action::Top => 0,
+        Action::Bottom => usize::MAX,
+        _ => return None,
+    };
+    Some(index.min(len.saturating_sub(1)))
+}
+
+fn count(n: usize) -> String {
+    match n {
+        1 => "1 item".to_string(),
+        n => format!("{n} items"),
+    }
+}
+
+/// The character a bookmark is set or looked up with, `None` for other keys.
+fn bookmark_key(key: Key) -> Option<char> {
+    match key.code {
+        KeyCode::Char(c)
+            if !key
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+        {
+            Some(c)
+        }
+        _ => None,
+    }
+}
+
+fn format_size(size: u64) -> String {
+    let (size, unit) = details::human_readable_size(size);
+    format!("{size}{unit}")
+}
+
+/// Everything the event loop works with.
+pub struct App {
+    pub options: ViewOptions,
+    pub navigator: Navigator,
+    tabs: tabs::Tabs,
+    // the second listing, once the dual-pane layout has been used
+    panes: Option<panes::Panes>,
+    // the left column of the Miller layout
+    parent_listing: miller::ParentListing,
+    // refreshes the listing and the preview when they change on disk
+    watcher: Option<Watcher>,
+    previewer: Previewer,
+    mime_types: MimeTypes,
+    script: Option<Script>,
+    operation: Option<Operation>,
+    // entries copied or cut, waiting to be pasted
+    clipboard: Option<(operation::Kind, Vec<PathBuf>)>,
+    prompt: Option<Prompt>,
+    filter_prompt: Option<filter::FilterPrompt>,
+    finder: Option<finder::Finder>,
+    // the expression being typed before a search starts
+    search_prompt: Option<input::LineEdit>,
+    journal: Journal,
+    bookmarks: Bookmarks,
+    view: View,
+    // shown at the bottom of the screen until the next key press
+    messages: Vec<String>,
+    // keys of a sequence typed so far, such as the first 'g' of 'gg'
+    pending_keys: Vec<Key>,
+    // number of rows visible in the listing, used for paging
+    page_height: usize,
+    pub should_quit: bool,
+    // set when the screen has been used by a child process and must be redrawn in full
+    pub needs_redraw: bool,
+}
+
+impl App {
+    pub fn new(options: ViewOptions, navigator: Navigator, script: Option<Script>) -> Self {
+        let mut app = App {
+            journal: Journal::new(&options.journal),
+            bookmarks: Bookmarks::new(&options.bookmarks),
+            options,
+            navigator,
+            tabs: tabs::Tabs::new(),
+            panes: None,
+            parent_listing: miller::ParentListing::new(),
+            watcher: None,
+            previewer: Previewer::new(),
+            mime_types: MimeTypes::new(),
+            script,
+            operation: None,
+            clipboard: None,
+            prompt: None,
+            filter_prompt: None,
+            finder: None,
+            search_prompt: None,
+            view: View::Directory,
+            messages: Vec::new(),
+            pending_keys: Vec::new(),
+            page_height: 1,
+            should_quit: false,
+            needs_redraw: false,
+        };
+        match Watcher::new() {
+            Ok(watcher) => app.watcher = Some(watcher),
+            Err(error) => app.show_message(format!("Changes will not be shown: {error}")),
+        }
+        if app.options.layout == layout::Layout::Dual {
+            if let Err(error) = app.open_panes() {
+                app.show_message(error.to_string());
+            }
+        }
+        app
+    }
+
+    pub fn show_message(&mut self, message: String) {
+        self.messages.push(message);
+    }
+
+    /// Picks up results of background work, called on every iteration of the event loop.
+    /// Returns whether anything shown has changed.
+    pub fn update(&mut self) -> bool {
+        let errors = self.navigator.take_errors();
+        let mut changed = !errors.is_empty();
+        self.messages.extend(errors);
+        if self.shows_preview() {
+            if let Some(item) = self.navigator.selected_item() {
+                changed |= self.previewer.request(&item.path, &self.options);
+            }
+            changed |= self.previewer.poll();
+        }
+        if self.options.layout == layout::Layout::Miller {
+            changed |= self
+                .parent_listing
+                .update(self.navigator.cwd(), &self.options);
+        }
+        changed |= self.watch();
+        if self
+            .options
+            .entry_format
+            .iter()
+            .any(|column| matches!(column.column_type, ColumnType::MimeType))
+        {
+            self.mime_types
+                .request(visible_items(&self.navigator, self.page_height));
+            if let (layout::Layout::Dual, Some(panes)) = (self.options.layout, &self.panes) {
+                let navigator = &panes.other.navigator;
+                self.mime_types
+                    .request(visible_items(navigator, self.page_height));
+            }
+            changed |= self.mime_types.poll();
+        }
+
+        if let Some(finder) = self.finder.as_mut() {
+            changed |= finder.update();
+        }
+        if let View::Search(view) = &mut self.view {
+            changed |= view.update();
+        }
+
+        // a running operation shows its progress
+        if let Some(operation) = self.operation.as_mut() {
+            changed = true;
+            if let Some(report) = operation.poll() {
+                let kind = self.operation.take().unwrap().kind;
+                self.finish_operation(kind, report);
+            }
+        }
+        changed
+    }
+
+    /// Refreshes what changed on disk since the last call, returns whether anything did.
+    fn watch(&mut self) -> bool {
+        let previewed = match self.shows_preview() {
+            true => self.navigator.selected_item().map(|item| item.path.clone()),
+            false => None,
+        };
+        let watcher = match self.watcher.as_mut() {
+            Some(watcher) => watcher,
+            None => return false,
+        };
+        watcher.watch_directories(&self.navigator.listed_dirs());
+        watcher.watch_preview(previewed.as_deref());
+
+        let changes = watcher.poll();
+        if changes.directory {
+            // the cursor stays on the same name
+            if let Err(error) = self.navigator.reload(&self.options) {
+                self.show_message(error.to_string());
+            }
+            self.parent_listing.invalidate();
+        }
+        if changes.preview {
+            self.previewer.invalidate();
+        }
+        changes != Changes::default()
+    }
+
+    /// The entries file operations and commands act on: the marked ones, or the selected
+    /// one when nothing is marked.
+    fn targets(&self) -> Vec<PathBuf> {
+        let marked = self.navigator.marked_items();
+        if marked.is_empty() {
+            self.navigator
+                .selected_item()
+                .map(|item| vec![item.path.clone()])
+                .unwrap_or_default()
+        } else {
+            marked.iter().map(|item| item.path.clone()).collect()
+        }
+    }
+
+    /// Puts the targets into the clipboard, to be copied or moved by the next paste.
+    fn yank(&mut self, kind: operation::Kind, verb: &str) {
+        let targets = self.targets();
+        self.show_message(format!("{} to {verb}", count(targets.len())));
+        self.clipboard = Some((kind, targets));
+        self.navigator.clear_marks();
+    }
+
+    fn start_operation(
+        &mut self,
+        kind: operation::Kind,
+        sources: Vec<PathBuf>,
+        destination: Option<PathBuf>,
+    ) -> std::io::Result<()> {
+        if self.operation.is_some() {
+            return Err(std::io::Error::other("Another operation is in progress"));
+        }
+        if !sources.is_empty() {
+            self.operation = Some(Operation::start(kind, sources, destination));
+        }
+        Ok(())
+    }
+
+    fn finish_operation(&mut self, kind: operation::Kind, report: operation::Report) {
+        let change_kind = match kind {
+            operation::Kind::Move => Some(journal::ChangeKind::Move),
+            operation::Kind::Trash => Some(journal::ChangeKind::Trash),
+            operation::Kind::Copy | operation::Kind::Delete => None,
+        };
+        if let Some(change_kind) = change_kind {
+            let changes = report
+                .completed
+                .iter()
+                .filter_map(|(from, to)| match to {
+                    Some(to) if to != from => {
+                        journal::Change::new(change_kind, from.clone(), to.clone()).ok()
+                    }
+                    _ => None,
+                })
+                .collect();
+            self.journal.record(journal::Entry {
+                description: format!(
+                    "{} {}",
+                    kind.name().to_lowercase(),
+                    count(report.completed.len())
+                ),
+                changes,
+            });
+        }
+
+        self.messages.extend(report.errors);
+        if report.cancelled {
+            self.show_message(format!("{} cancelled", kind.name()));
+        } else {
+            let mut summary = format!("{} {}", kind.past_name(), count(report.completed.len()));
+            if report.skipped > 0 {
+                summary += &format!(", skipped {}", count(report.skipped));
+            }
+            self.show_message(summary);
+        }
+
+        if let Err(error) = self.navigator.reload(&self.options) {
+            self.show_message(error.to_string());
+        }
+        self.reload_other_pane();
+        self.parent_listing.invalidate();
+        self.previewer.invalidate();
+    }
+
+    fn resolve_conflict(&mut self, key: Key) {
+        let operation = match self.operation.as_mut() {
+            Some(operation) => operation,
+            None => return,
+        };
+        let (resolution, apply_to_all) = match key.code {
+            KeyCode::Char('o') => (Resolution::Overwrite, false),
+            KeyCode::Char('O') => (Resolution::Overwrite, true),
+            KeyCode::Char('s') => (Resolution::Skip, false),
+            KeyCode::Char('S') => (Resolution::Skip, true),
+            KeyCode::Char('r') => (Resolution::Rename, false),
+            KeyCode::Char('R') => (Resolution::Rename, true),
+            KeyCode::Esc => return operation.cancel(),
+            _ => return,
+        };
+        operation.resolve(resolution, apply_to_all);
+    }
+
+    fn answer_prompt(&mut self, prompt: Prompt, key: Key) -> std::io::Result<()> {
+        match prompt {
+            Prompt::Delete(paths) if key.code == KeyCode::Char('y') => {
+                self.navigator.clear_marks();
+                self.start_operation(operation::Kind::Delete, paths, None)
+            }
+            Prompt::Purge(items) if key.code == KeyCode::Char('y') => {
+                let mut purged = 0;
+                for item in items {
+                    match item.purge() {
+                        Ok(()) => purged += 1,
+                        Err(error) => {
+                            let name = item.name.to_string_lossy();
+                            self.show_message(format!("{name}: {error}"))
+                        }
+                    }
+                }
+                self.show_message(format!("Deleted {} permanently", count(purged)));
+                if let View::Trash(view) = &mut self.view {
+                    view.reload();
+                }
+                Ok(())
+            }
+            Prompt::Rename(renames) if key.code == KeyCode::Char('y') => {
+                self.navigator.clear_marks();
+                self.rename(renames)
+            }
+            Prompt::SetBookmark => {
+                if let Some(c) = bookmark_key(key) {
+                    self.bookmarks.set(c, self.navigator.cwd());
+                    self.show_message(format!("Bookmarked as '{c}'"));
+                }
+                Ok(())
+            }
+            Prompt::JumpToBookmark => match bookmark_key(key) {
+                Some(c) => self.jump_to_bookmark(c),
+                None => Ok(()),
+            },
+            Prompt::Delete(_) | Prompt::Purge(_) | Prompt::Rename(_) => Ok(()),
+        }
+    }
+
+    fn prompt_text(&self) -> Option<String> {
+        if let Some(path) = self.operation.as_ref().and_then(|op| op.conflict.as_ref()) {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            return Some(format!(
+                "'{name}' already exists: [o]verwrite, [s]kip, [r]ename (uppercase for all), <Esc> to cancel"
+            ));
+        }
+        self.prompt.as_ref().map(|prompt| match prompt {
+            Prompt::Delete(paths) => format!("Delete {}? [y/N]", count(paths.len())),
+            Prompt::Purge(items) => format!(
+                "Delete {} from the trash permanently? [y/N]",
+                count(items.len())
+            ),
+            Prompt::Rename(renames) => format!("Rename {}? [y/N]", count(renames.len())),
+            Prompt::SetBookmark => String::from("Bookmark the current directory as (press a key)"),
+            Prompt::JumpToBookmark => {
+                let keys: String = self.bookmarks.list().iter().map(|(c, _, _)| *c).collect();
+                format!("Jump to bookmark: {keys}")
+            }
+        })
+    }
+
+    pub fn handle_key(&mut self, key: Key) {
+        self.messages.clear();
+
+        // a running operation waits for the conflict to be resolved
+        if self
+            .operation
+            .as_ref()
+            .is_some_and(|operation| operation.conflict.is_some())
+        {
+            return self.resolve_conflict(key);
+        }
+        if let Some(prompt) = self.prompt.take() {
+            if let Err(error) = self.answer_prompt(prompt, key) {
+                self.show_message(error.to_string());
+            }
+            return;
+        }
+        if self.finder.is_some() {
+            if let Err(error) = self.handle_finder_key(key) {
+                self.show_message(error.to_string());
+            }
+            return;
+        }
+        if self.filter_prompt.is_some() {
+            return self.handle_filter_key(key);
+        }
+        if self.search_prompt.is_some() {
+            if let Err(error) = self.handle_search_prompt_key(key) {
+                self.show_message(error);
+            }
+            return;
+        }
+        if let View::Rename(_) = self.view {
+            if let Err(error) = self.handle_rename_key(key) {
+                self.show_message(error.to_string());
+            }
+            return;
+        }
+
+        self.pending_keys.push(key);
+
+        match self.options.keys.lookup(&self.pending_keys) {
+            Lookup::Action(action) => {
+                self.pending_keys.clear();
+                if let Err(error) = self.perform(action) {
+                    self.show_message(error.to_string());
+                }
+            }
+            Lookup::Command(name) => {
+                self.pending_keys.clear();
+                if let Err(error) = self.run_command(&name) {
+                    self.show_message(error.to_string());
+                }
+            }
+            Lookup::Pending => {}
+            Lookup::None => self.pending_keys.clear(),
+        }
+    }
+
+    fn handle_finder_key(&mut self, key: Key) -> std::io::Result<()> {
+        let finder = match self.finder.as_mut() {
+            Some(finder) => finder,
+            None => return Ok(()),
+        };
+        match key.code {
+            KeyCode::Esc => self.finder = None,
+            KeyCode::Enter => {
+                let path = finder.selected_path();
+                self.finder = None;
+                if let Some(path) = path {
+                    self.jump_to(&path)?;
+                }
+            }
+            _ => finder.handle_key(key),
+        }
+        Ok(())
+    }
+
+    /// Opens the directory containing `path` with it selected.
+    fn jump_to(&mut self, path: &Path) -> std::io::Result<()> {
+        let (dir, name) = match (path.parent(), path.file_name()) {
+            (Some(dir), Some(name)) => (dir, name.to_string_lossy().to_string()),
+            _ => return self.navigator.change_dir(path, &self.options),
+        };
+        self.navigator.change_dir(dir, &self.options)?;
+        if !self.navigator.select_name(&name) {
+            // hidden by the filter
+            self.navigator.set_filter(None);
+            self.navigator.select_name(&name);
+        }
+        Ok(())
+    }
+
+    fn handle_filter_key(&mut self, key: Key) {
+        let prompt = match self.filter_prompt.as_mut() {
+            Some(prompt) => prompt,
+            None => return,
+        };
+        match key.code {
+            KeyCode::Esc => {
+                let previous = self.filter_prompt.take().unwrap().previous;
+                self.navigator.set_filter(previous);
+            }
+            // the filter is applied already
+            KeyCode::Enter => self.filter_prompt = None,
+            KeyCode::Tab => {
+                prompt.mode = prompt.mode.next();
+                if let Ok(filter) = prompt.filter() {
+                    self.navigator.set_filter(filter);
+                }
+            }
+            _ => {
+                if prompt.input.handle_key(key) {
+                    if let Ok(filter) = prompt.filter() {
+                        self.navigator.set_filter(filter);
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_search_prompt_key(&mut self, key: Key) -> Result<(), String> {
+        let input = match self.search_prompt.as_mut() {
+            Some(input) => input,
+            None => return Ok(()),
+        };
+        match key.code {
+            KeyCode::Esc => self.search_prompt = None,
+            KeyCode::Enter => {
+                let pattern = input.text().to_string();
+                let regex = crate::search::build_regex(&pattern)?;
+                self.search_prompt = None;
+                if !pattern.is_empty() {
+                    let root = self.navigator.cwd();
+                    let search =
+                        crate::search::Search::start(root, regex, self.options.show_hidden);
+                    self.view = View::Search(search::SearchView::new(root, &pattern, search));
+                }
+            }
+            _ => {
+                input.handle_key(key);
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_rename_key(&mut self, key: Key) -> std::io::Result<()> {
+        let dialog = match &mut self.view {
+            View::Rename(dialog) => dialog,
+            _ => return Ok(()),
+        };
+        match key.code {
+            KeyCode::Esc => self.view = View::Directory,
+            KeyCode::Enter => {
+                let renames = dialog.renames().clone().map_err(std::io::Error::other)?;
+                self.view = View::Directory;
+                self.navigator.clear_marks();
+                self.rename(renames)?;
+            }
+            _ => dialog.handle_key(key),
+        }
+        Ok(())
+    }
+
+    pub fn perform(&mut self, action: Action) -> std::io::Result<()> {
+        match self.view {
+            View::Trash(_) => return self.perform_in_trash(action),
+            View::Search(_) => return self.perform_in_search(action),
+            View::Bookmarks(_) => return self.perform_in_bookmarks(action),
+            View::Directory | View::Rename(_) => {}
+        }
+
+        let navigator = &mut self.navigator;
+        let options = &mut self.options;
+
+        if let Some(index) = move_cursor(
+            action,
+            navigator.selected_index(),
+            navigator.items().len(),
+            self.page_height,
+        ) {
+            navigator.select_index(index);
+            return Ok(());
+        }
+
+        match action {
+            Action::MoveUp
+            | Action::MoveDown
+            | Action::PageUp
+            | Action::PageDown
+            | Action::Top
+            | Action::Bottom => {}
+            Action::Enter => navigator.enter(options)?,
+            Action::Parent => navigator.parent(options)?,
+            Action::Back => navigator.back(options)?,
+            Action::Forward => navigator.forward(options)?,
+            Action::ToggleTree => {
+                navigator.toggle_tree(options)?;
+                self.previewer.invalidate();
+            }
+            Action::ToggleExpanded => navigator.toggle_expanded(options)?,
+            Action::ToggleHidden => {
+                options.show_hidden ^= true;
+                navigator.reload(options)?;
+            }
+            Action::CycleSort => {
+                options.sort.by = options.sort.by.next();
+                navigator.reload(options)?;
+            }
+            Action::ReverseSort => {
+                options.sort.reverse ^= true;
+                navigator.reload(options)?;
+            }
+            Action::TogglePreview => {
+                options.preview.enabled ^= true;
+                self.previewer.invalidate();
+            }
+            Action::Reload => {
+                navigator.reload(options)?;
+                self.parent_listing.invalidate();
+                self.previewer.invalidate();
+            }
+            Action::Copy => self.yank(operation::Kind::Copy, "copy"),
+            Action::Cut => self.yank(operation::Kind::Move, "move"),
+            Action::Paste => {
+                let (kind, sources) = match &self.clipboard {
+                    Some((kind, sources)) => (*kind, sources.clone()),
+                    None => return Ok(()),
+                };
+                let destination = navigator.cwd().to_path_buf();
+                self.start_operation(kind, sources, Some(destination))?;
+                // moved entries cannot be pasted twice
+                if kind == operation::Kind::Move {
+                    self.clipboard = None;
+                }
+            }
+            Action::Trash => {
+                let targets = self.targets();
+                self.navigator.clear_marks();
+                self.start_operation(operation::Kind::Trash, targets, None)?;
+            }
+            Action::Delete => {
+                let targets = self.targets();
+                if !targets.is_empty() {
+                    self.prompt = Some(Prompt::Delete(targets));
+                }
+            }
+            Action::ShowTrash => self.view = View::Trash(trash::TrashView::new()),
+            Action::SetBookmark => self.prompt = Some(Prompt::SetBookmark),
+            Action::JumpToBookmark => self.prompt = Some(Prompt::JumpToBookmark),
+            Action::ShowBookmarks => {
+                self.view = View::Bookmarks(bookmarks::BookmarksView::new(&self.bookmarks));
+            }
+            Action::EmptyTrash => {
+                let items = crate::trash::items();
+                if !items.is_empty() {
+                    self.prompt = Some(Prompt::Purge(items));
+                }
+            }
+            Action::Restore => {}
+            Action::Undo | Action::Redo => {
+                let result = match action {
+                    Action::Undo => self.journal.undo(),
+                    _ => self.journal.redo(),
+                };
+                let message = match result? {
+                    Some(description) if action == Action::Undo => format!("Undid {description}"),
+                    Some(description) => format!("Redid {description}"),
+                    None => String::from("Nothing to do"),
+                };
+                self.show_message(message);
+                self.navigator.reload(&self.options)?;
+                self.reload_other_pane();
+                self.previewer.invalidate();
+            }
+            Action::Rename => {
+                let marked = navigator.marked_items();
+                let items = match navigator.selected_item() {
+                    Some(item) if marked.is_empty() => vec![item],
+                    _ => marked,
+                };
+                if !items.is_empty() {
+                    let dialog = rename::RenameDialog::new(&items);
+                    self.view = View::Rename(dialog);
+                }
+            }
+            Action::Filter => {
+                self.filter_prompt = Some(filter::FilterPrompt::new(navigator.filter()));
+            }
+            Action::ClearFilter => navigator.set_filter(None),
+            Action::Search => self.search_prompt = Some(input::LineEdit::default()),
+            Action::Find => {
+                let finder = finder::Finder::new(navigator.cwd(), options.show_hidden);
+                self.finder = Some(finder);
+            }
+            Action::BulkRename => {
+                let result = self.bulk_rename();
+                // the editor used the screen
+                self.needs_redraw = true;
+                result?;
+            }
+            Action::NewTab => {
+                let navigator = Navigator::new(navigator.cwd(), options)?;
+                self.open_tab(navigator);
+            }
+            Action::OpenInTab => match navigator.selected_item() {
+                Some(item) if item.is_dir() => {
+                    let navigator = Navigator::new(&item.path, options)?;
+                    self.open_tab(navigator);
+                }
+                _ => {}
+            },
+            Action::CloseTab => {
+                if self.tabs.close(&mut self.navigator, &mut self.options.sort) {
+                    self.navigator.reload(&self.options)?;
+                    self.previewer.invalidate();
+                } else {
+                    self.show_message(String::from("The last tab cannot be closed"));
+                }
+            }
+            Action::NextTab => {
+                let index = (self.tabs.active() + 1) % self.tabs.len();
+                self.switch_tab(index)?;
+            }
+            Action::PreviousTab => {
+                let index = (self.tabs.active() + self.tabs.len() - 1) % self.tabs.len();
+                self.switch_tab(index)?;
+            }
+            Action::SwitchToTab(number) => {
+                self.switch_tab(usize::from(number).saturating_sub(1))?
+            }
+            Action::MoveTabLeft => self.tabs.move_active(-1),
+            Action::MoveTabRight => self.tabs.move_active(1),
+            Action::CycleLayout => {
+                options.layout = options.layout.next();
+                if options.layout == layout::Layout::Dual && self.panes.is_none() {
+                    self.open_panes()?;
+                }
+                self.previewer.invalidate();
+            }
+            Action::SwitchPane | Action::SwapPanes => {
+                let panes = match self.panes.as_mut() {
+                    Some(panes) if options.layout == layout::Layout::Dual => panes,
+                    _ => return Ok(()),
+                };
+                if action == Action::SwitchPane {
+                    panes.switch(navigator, &mut options.sort);
+                } else {
+                    panes.swap(navigator, &mut options.sort);
+                }
+                navigator.reload(options)?;
+                self.previewer.invalidate();
+            }
+            Action::CopyToPane | Action::MoveToPane => {
+                let destination = match &self.panes {
+                    Some(panes) if options.layout == layout::Layout::Dual => {
+                        panes.other.navigator.cwd().to_path_buf()
+                    }
+                    _ => {
+                        self.show_message(String::from("There is no other pane"));
+                        return Ok(());
+                    }
+                };
+                let kind = match action {
+                    Action::CopyToPane => operation::Kind::Copy,
+                    _ => operation::Kind::Move,
+                };
+                let targets = self.targets();
+                self.navigator.clear_marks();
+                self.start_operation(kind, targets, Some(destination))?;
+            }
+            Action::ToggleMark => navigator.toggle_mark(),
+            Action::MarkAll => navigator.mark_all(),
+            Action::InvertMarks => navigator.invert_marks(),
+            Action::ClearMarks => navigator.clear_marks(),
+            Action::VisualMode => navigator.toggle_visual_mode(),
+            Action::Cancel => {
+                navigator.cancel_visual_mode();
+                if let Some(operation) = self.operation.as_mut() {
+                    operation.cancel();
+                }
+            }
+            // <Esc> leaves visual mode first
+            Action::Quit if navigator.in_visual_mode() => navigator.cancel_visual_mode(),
+            Action::Quit if self.operation.is_some() => {
+                self.show_message(String::from(
+                    "An operation is in progress, wait for it or cancel it first",
+                ));
+            }
+            Action::Quit => self.should_quit = true,
+        }
+        Ok(())
+    }
+
+    fn perform_in_trash(&mut self, action: Action) -> std::io::Result<()> {
+        let view = match &mut self.view {
+            View::Trash(view) => view,
+            _ => return Ok(()),
+        };
+
+        if let Some(index) = move_cursor(
+            action,
+            view.selected_index,
+            view.items.len(),
+            self.page_height,
+        ) {
+            view.select_index(index);
+            return Ok(());
+        }
+
+        match action {
+            Action::Restore => {
+                if let Some(item) = view.selected_item() {
+                    item.restore()?;
+                    let message = format!("Restored '{}'", item.original_path.display());
+                    view.reload();
+                    self.show_message(message);
+                }
+            }
+            Action::Trash | Action::Delete => {
+                if let Some(item) = view.selected_item() {
+                    self.prompt = Some(Prompt::Purge(vec![item.clone()]));
+                }
+            }
+            Action::EmptyTrash if !view.items.is_empty() => {
+                self.prompt = Some(Prompt::Purge(view.items.clone()));
+            }
+            Action::Reload => view.reload(),
+            Action::ShowTrash | Action::Parent | Action::Cancel | Action::Quit => {
+                self.view = View::Directory;
+                // restored entries may have appeared
+                self.navigator.reload(&self.options)?;
+                self.previewer.invalidate();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Lets the user edit the names of the marked entries, or of all of them when
+    /// nothing is marked, and asks before renaming.
+    fn bulk_rename(&mut self) -> std::io::Result<()> {
+        let marked = self.navigator.marked_items();
+        let items = if marked.is_empty() {
+            self.navigator.items().iter().collect()
+        } else {
+            marked
+        };
+        let names: Vec<String> = items.iter().map(|item| item.name.clone()).collect();
+        if names.is_empty() {
+            return Ok(());
+        }
+
+        let edited = crate::rename::edit_names(&names)?;
+        let pairs: Vec<_> = items
+            .iter()
+            .map(|item| item.path.clone())
+            .zip(edited)
+            .collect();
+        let renames = crate::rename::prepare(&pairs).map_err(std::io::Error::other)?;
+        if renames.is_empty() {
+            self.show_message(String::from("No names were changed"));
+        } else {
+            self.prompt = Some(Prompt::Rename(renames));
+        }
+        Ok(())
+    }
+
+    fn rename(&mut self, renames: Vec<(PathBuf, PathBuf)>) -> std::io::Result<()> {
+        let mut changes = Vec::new();
+        let result = crate::rename::apply(&crate::rename::plan(&renames), &mut changes);
+        self.journal.record(journal::Entry {
+            description: format!("renaming {}", count(renames.len())),
+            changes,
+        });
+        if result.is_ok() {
+            self.show_message(format!("Renamed {}", count(renames.len())));
+        }
+
+        self.navigator.reload(&self.options)?;
+        self.previewer.invalidate();
+        result
+    }
+
+    fn perform_in_search(&mut self, action: Action) -> std::io::Result<()> {
+        let view = match &mut self.view {
+            View::Search(view) => view,
+            _ => return Ok(()),
+        };
+
+        if let Some(index) = move_cursor(
+            action,
+            view.selected_index,
+            view.hits.len(),
+            self.page_height,
+        ) {
+            view.select_index(index);
+            return Ok(());
+        }
+
+        match action {
+            Action::Enter => {
+                if let Some((path, line)) = view.selected() {
+                    let status = crate::terminal::edit(&path, Some(line));
+                    self.needs_redraw = true;
+                    status?;
+                }
+            }
+            // shows the file of the selected line in the listing
+            Action::Parent => {
+                let selected = view.selected();
+                self.view = View::Directory;
+                if let Some((path, _)) = selected {
+                    self.jump_to(&path)?;
+                }
+            }
+            Action::Cancel => view.search.cancel(),
+            Action::Search => self.search_prompt = Some(input::LineEdit::default()),
+            Action::Quit => self.view = View::Directory,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Whether the preview pane is shown beside the listing.
+    fn shows_preview(&self) -> bool {
+        match self.options.layout {
+            layout::Layout::Single => self.options.preview.enabled,
+            layout::Layout::Dual => false,
+            layout::Layout::Miller => true,
+        }
+    }
+
+    /// Creates the second pane of the dual-pane layout in the current directory.
+    fn open_panes(&mut self) -> std::io::Result<()> {
+        let navigator = Navigator::new(self.navigator.cwd(), &self.options)?;
+        self.panes = Some(panes::Panes::new(tabs::Tab {
+            navigator,
+            sort: self.options.sort.clone(),
+        }));
+        Ok(())
+    }
+
+    fn reload_other_pane(&mut self) {
+        if let Some(panes) = self.panes.as_mut() {
+            if let Err(error) = panes.other.reload(&self.options) {
+                self.messages.push(error.to_string());
+            }
+        }
+    }
+
+    /// Shows a new tab with the current sort options.
+    fn open_tab(&mut self, navigator: Navigator) {
+        let tab = tabs::Tab {
+            navigator,
+            sort: self.options.sort.clone(),
+        };
+        self.tabs
+            .open(tab, &mut self.navigator, &mut self.options.sort);
+        self.previewer.invalidate();
+    }
+
+    fn switch_tab(&mut self, index: usize) -> std::io::Result<()> {
+        if self
+            .tabs
+            .switch(index, &mut self.navigator, &mut self.options.sort)
+        {
+            // the directory may have changed in the background
+            self.navigator.reload(&self.options)?;
+            self.previewer.invalidate();
+        }
+        Ok(())
+    }
+
+    fn jump_to_bookmark(&mut self, key: char) -> std::io::Result<()> {
+        let path = match self.bookmarks.get(key) {
+            Some(path) => path.to_path_buf(),
+            None => {
+                self.show_message(format!("No bookmark '{key}'"));
+                return Ok(());
+            }
+        };
+        if path.is_dir() {
+            self.navigator.change_dir(&path, &self.options)
+        } else {
+            self.jump_to(&path)
+        }
+    }
+
+    fn perform_in_bookmarks(&mut self, action: Action) -> std::io::Result<()> {
+        let view = match &mut self.view {
+            View::Bookmarks(view) => view,
+            _ => return Ok(()),
+        };
+
+        if let Some(index) = move_cursor(
+            action,
+            view.selected_index,
+            view.items.len(),
+            self.page_height,
+        ) {
+            view.select_index(index);
+            return Ok(());
+        }
+
+        match action {
+            Action::Enter => {
+                if let Some((key, _, _)) = view.selected_item() {
+                    let key = *key;
+                    self.view = View::Directory;
+                    self.jump_to_bookmark(key)?;
+                }
+            }
+            Action::Trash | Action::Delete => {
+                if let Some((key, _, _)) = view.selected_item() {
+                    let key = *key;
+                    if self.bookmarks.remove(key) {
+                        view.reload(&self.bookmarks);
+                        self.show_message(format!("Removed bookmark '{key}'"));
+                    } else {
+                        self.show_message(String::from(
+                            "Bookmarks from the configuration cannot be removed",
+                        ));
+                    }
+                }
+            }
+            Action::ShowBookmarks | Action::Parent | Action::Cancel | Action::Quit => {
+                self.view = View::Directory;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn run_command(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let selected = self.navigator.selected_item().map(|item| item.path.clone());
+        let targets = self.targets();
+        let script = match self.script.as_mut() {
+            Some(script) => script,
+            None => return Ok(()),
+        };
+        let (requests, error) =
+            script.run_command(name, self.navigator.cwd(), selected.as_deref(), &targets);
+
+        // requests made before an error are applied, a shell command may have run already
+        for request in requests {
+            match request {
+                script::Request::ChangeDir(path) => {
+                    self.navigator.change_dir(&path, &self.options)?
+                }
+                script::Request::Notify(message) => self.show_message(message),
+                script::Request::Redraw => {
+                    self.needs_redraw = true;
+                    // the command may have changed the directory contents
+                    self.navigator.reload(&self.options)?;
+                    self.previewer.invalidate();
+                }
+            }
+        }
+        match error {
+            Some(error) => Err(error.into()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn draw<B: tui::backend::Backend>(&mut self, f: &mut tui::Frame<B>) {
+        let prompt = self.prompt_text();
+        let has_input = matches!(self.view, View::Rename(_))
+            || self.filter_prompt.is_some()
+            || self.search_prompt.is_some();
+        let message_height = (self.messages.len().min(MAX_MESSAGE_LINES)
+            + usize::from(prompt.is_some() || has_input)) as u16;
+        let progress_height = if self.operation.is_some() {
+            PROGRESS_HEIGHT
+        } else {
+            0
+        };
+        let tab_bar_height = u16::from(self.tabs.len() > 1);
+        let chunks = Layout::default()
+            .constraints([
+                Constraint::Length(tab_bar_height),
+                Constraint::Min(0),
+                Constraint::Length(progress_height),
+                Constraint::Length(message_height),
+            ])
+            .split(f.size());
+
+        if tab_bar_height > 0 {
+            self.tabs
+                .draw(f, chunks[0], self.navigator.cwd(), highlight_style());
+        }
+        self.draw_main(f, chunks[1]);
+        if let Some(finder) = &self.finder {
+            finder.draw(f, chunks[1], highlight_style());
+        }
+
+        if let Some(operation) = &self.operation {
+            draw_progress(f, chunks[2], operation);
+        }
+
+        let mut message_lines: Vec<_> = self
+            .messages
+            .iter()
+            .map(|message| {
+                tui::text::Spans::from(Span::styled(
+                    message.as_str(),
+                    Style::default().fg(Color::LightRed),
+                ))
+            })
+            .collect();
+        let prompt_style = Style::default().fg(Color::LightYellow);
+        if let Some(prompt) = prompt {
+            message_lines.push(tui::text::Spans::from(Span::styled(prompt, prompt_style)));
+        } else if let Some(input) = &self.search_prompt {
+            message_lines.push(input.spans(String::from("Search for (regex): "), prompt_style));
+        } else if let Some(filter_prompt) = &self.filter_prompt {
+            message_lines.push(filter_prompt.spans(prompt_style));
+        } else if let View::Rename(dialog) = &self.view {
+            message_lines.push(dialog.input.spans(dialog.prompt(), prompt_style));
+        }
+        f.render_widget(Paragraph::new(message_lines), chunks[3]);
+    }
+
+    fn draw_main<B: tui::backend::Backend>(&mut self, f: &mut tui::Frame<B>, area: Rect) {
+        if let View::Trash(view) = &self.view {
+            view.draw(f, area, highlight_style());
+            // without the borders and the header
+            self.page_height = area.height.saturating_sub(3).max(1) as usize;
+            return;
+        }
+        if let View::Bookmarks(view) = &self.view {
+            view.draw(f, area, highlight_style());
+            // without the borders and the header
+            self.page_height = area.height.saturating_sub(3).max(1) as usize;
+            return;
+        }
+        if let View::Rename(dialog) = &self.view {
+            return dialog.draw(f, area);
+        }
+        if let View::Search(view) = &self.view {
+            view.draw(f, area, highlight_style());
+            // without the borders
+            self.page_height = area.height.saturating_sub(2).max(1) as usize;
+            return;
+        }
+        if let Some(Prompt::Rename(renames)) = &self.prompt {
+            let names: Vec<_> = renames
+                .iter()
+                .map(|(from, to)| (rename::file_name(from), rename::file_name(to)))
+                .collect();
+            let title = Span::raw(format!("Rename {}", count(renames.len())));
+            return rename::draw_preview(f, area, title, &names);
+        }
+
+        if let (layout::Layout::Dual, Some(panes)) = (self.options.layout, &self.panes) {
+            let halves = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(area);
+            let (focused_area, other_area) = if panes.left_focused() {
+                (halves[0], halves[1])
+            } else {
+                (halves[1], halves[0])
+            };
+            let other = &panes.other;
+            draw_listing(
+                f,
+                other_area,
+                &other.navigator,
+                &self.mime_types,
+                &self.options,
+                &other.sort,
+                false,
+            );
+            self.draw_listing(f, focused_area);
+            return;
+        }
+
+        if self.options.layout == layout::Layout::Miller {
+            let columns = miller::columns(area, &self.options.miller);
+            self.parent_listing.draw(
+                f,
+                columns[0],
+                self.navigator.cwd(),
+                inactive_highlight_style(),
+            );
+            self.draw_listing(f, columns[1]);
+            self.draw_preview(f, columns[2]);
+            return;
+        }
+
+        let options = &self.options;
+
+        let panes = if self.shows_preview() {
+            Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Percentage(100 - options.preview.width),
+                    Constraint::Percentage(options.preview.width),
+                ])
+                .split(area)
+        } else {
+            vec![area]
+        };
+
+        self.draw_listing(f, panes[0]);
+        if let Some(area) = panes.get(1) {
+            self.draw_preview(f, *area);
+        }
+    }
+
+    fn draw_preview<B: tui::backend::Backend>(&self, f: &mut tui::Frame<B>, area: Rect) {
+        if let Some(item) = self.navigator.selected_item() {
+            let block = Block::default()
+                .borders(Borders::ALL)
+                .title(item.name.as_str());
+            preview::render(f, area, block, self.previewer.content());
+        }
+    }
+
+    fn draw_listing<B: tui::backend::Backend>(&mut self, f: &mut tui::Frame<B>, area: Rect) {
+        let options = &self.options;
+        draw_listing(
+            f,
+            area,
+            &self.navigator,
+            &self.mime_types,
+            options,
+            &options.sort,
+            true,
+        );
+
+        // without the borders
+        self.page_height = area.height.saturating_sub(2).max(1) as usize;
+    }
+}
+
+/// The entries that can be on screen, the listing scrolls just enough to show the cursor.
+fn visible_items(navigator: &Navigator, page_height: usize) -> &[Item] {
+    let items = navigator.items();
+    let selected = navigator.selected_index();
+    let end = (selected + page_height + 1).min(items.len());
+    &items[selected.saturating_sub(page_height).min(end)..end]
+}
+
+/// The entries of a directory, the cursor is dimmed unless the listing has the focus.
+fn draw_listing<B: tui::backend::Backend>(
+    f: &mut tui::Frame<B>,
+    area: Rect,
+    navigator: &Navigator,
+    mime_types: &MimeTypes,
+    options: &ViewOptions,
+    sort: &SortOptions,
+    focused: bool,
+) {
+    let mut state = TableState::default();
+    state.select(Some(navigator.selected_index()));
+
+    let table_data = model::get_table_data(
+        navigator.items(),
+        options,
+        area.width,
+        |index| navigator.is_marked(index),
+        |index| navigator.tree_guide(index),
+        |item| mime_types.get(item),
+    );
+    let mut title = format!(
+        "{} [{}{}]",
+        navigator.cwd().display(),
+        sort.by.name(),
+        if sort.reverse { ", reversed" } else { "" }
+    );
+    if let Some(filter) = navigator.filter() {
+        title += &format!(" [{}: {}]", filter.mode.name(), filter.pattern);
+    }
+    if navigator.in_tree_mode() {
+        title += " [tree]";
+    }
+    let cursor_style = if focused {
+        highlight_style()
+    } else {
+        inactive_highlight_style()
+    };
+    let list = Table::new(table_data.rows)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .widths(&table_data.widths)
+        .highlight_style(cursor_style);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_progress<B: tui::backend::Backend>(
+    f: &mut tui::Frame<B>,
+    area: Rect,
+    operation: &Operation,
+) {
+    let progress = &operation.progress;
+    let title = format!(
+        "{} {}/{} ({}/s)",
+        operation.kind.name(),
+        progress.files_done,
+        progress.files_total,
+        format_size(progress.throughput())
+    );
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let rows = Layout::default()
+        .constraints([Constraint::Length(1), Constraint::Length(1)])
+        .split(block.inner(area));
+    f.render_widget(block, area);
+
+    let gauge_style = Style::default().fg(Color::LightYellow);
+    let current = progress
+        .current
+        .as_ref()
+        .and_then(|path| path.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    f.render_widget(
+        LineGauge::default()
+            .gauge_style(gauge_style)
+            .ratio(progress.file_ratio())
+            .label(current),
+        rows[0],
+    );
+    f.render_widget(
+        LineGauge::default()
+            .gauge_style(gauge_style)
+            .ratio(progress.ratio())
+            .label(format!(
+                "{} of {}",
+                format_size(progress.bytes_done),
+                format_size(progress.bytes_total)
+            )),
+        rows[1],
+    );
+}
@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+
+use tui::layout::{Constraint, Rect};
+use tui::style::*;
+use tui::widgets::*;
+
+use crate::bookmarks::Bookmarks;
+
+/// All bookmarks, by key.
+pub struct BookmarksView {
+    // key, directory and whether it is declared in the configuration
+    pub items: Vec<(char, PathBuf, bool)>,
+    pub selected_index: usize,
+}
+
+impl BookmarksView {
+    pub fn new(bookmarks: &Bookmarks) -> Self {
+        let mut view = BookmarksView {
+            items: Vec::new(),
+            selected_index: 0,
+        };
+        view.reload(bookmarks);
+        view
+    }
+
+    pub fn reload(&mut self, bookmarks: &Bookmarks) {
+        self.items = bookmarks
+            .list()
+            .into_iter()
+            .map(|(key, path, is_declared)| (key, path.to_path_buf(), is_declared))
+            .collect();
+        self.select_index(self.selected_index);
+    }
+
+    pub fn select_index(&mut self, index: usize) {
+        self.selected_index = index.min(self.items.len().saturating_sub(1));
+    }
+
+    pub fn selected_item(&self) -> Option<&(char, PathBuf, bool)> {
+        self.items.get(self.selected_index)
+    }
+
+    pub fn draw<B: tui::backend::Backend>(
+        &self,
+        f: &mut tui::Frame<B>,
+        area: Rect,
+        highlight_style: Style,
+    ) {
+        let rows: Vec<_> = self
+            .items
+            .iter()
+            .map(|(key, path, is_declared)| {
+                let origin = if *is_declared { "config" } else { "" };
+                Row::new(vec![
+                    key.to_string(),
+                    path.display().to_string(),
+                    origin.to_string(),
+                ])
+            })
+            .collect();
+
+        let mut state = TableState::default();
+        state.select(Some(self.selected_index));
+
+        let title = format!("Bookmarks ({})", self.items.len());
+        let widths = [
+            Constraint::Length(3),
+            Constraint::Percentage(80),
+            Constraint::Min(6),
+        ];
+        let table = Table::new(rows)
+            .header(
+                Row::new(vec!["Key", "Directory", ""])
+                    .style(Style::default().add_modifier(Modifier::BOLD)),
+            )
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .widths(&widths)
+            .highlight_style(highlight_style);
+        f.render_stateful_widget(table, area, &mut state);
+    }
+}
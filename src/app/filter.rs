@@ -0,0 +1,51 @@
+use tui::style::*;
+use tui::text::{Span, Spans};
+
+use super::input::LineEdit;
+use crate::model::filter::{Filter, FilterMode};
+
+/// The filter being typed, applied to the listing on every key press.
+pub struct FilterPrompt {
+    pub input: LineEdit,
+    pub mode: FilterMode,
+    // restored when the prompt is cancelled
+    pub previous: Option<Filter>,
+    // why the pattern typed so far is not a valid one
+    pub error: Option<String>,
+}
+
+impl FilterPrompt {
+    /// Starts editing the `current` filter.
+    pub fn new(current: Option<&Filter>) -> Self {
+        FilterPrompt {
+            input: LineEdit::new(current.map_or("", |filter| filter.pattern.as_str())),
+            mode: current.map_or(FilterMode::Substring, |filter| filter.mode),
+            previous: current.cloned(),
+            error: None,
+        }
+    }
+
+    /// The filter to apply, `None` when the pattern is empty. Invalid patterns are
+    /// reported as an error and change nothing.
+    pub fn filter(&mut self) -> Result<Option<Filter>, ()> {
+        self.error = None;
+        if self.input.text().is_empty() {
+            return Ok(None);
+        }
+        Filter::new(self.input.text(), self.mode)
+            .map(Some)
+            .map_err(|error| self.error = Some(error))
+    }
+
+    pub fn spans(&self, style: Style) -> Spans<'_> {
+        let prompt = format!("Filter ({}, <Tab> to switch): ", self.mode.name());
+        let mut spans = self.input.spans(prompt, style);
+        if let Some(error) = &self.error {
+            spans.0.push(Span::styled(
+                format!("  {error}"),
+                Style::default().fg(Color::LightRed),
+            ));
+        }
+        spans
+    }
+}
@@ -0,0 +1,195 @@
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{KeyCode, KeyModifiers};
+use tui::layout::{Constraint, Layout, Rect};
+use tui::style::*;
+use tui::text::{Span, Spans};
+use tui::widgets::*;
+
+use super::input::LineEdit;
+use crate::config::keys::Key;
+use crate::finder::{self, Walk};
+
+// share of the screen covered by the overlay
+const OVERLAY_PERCENT: u16 = 80;
+
+/// A path matching the pattern: its index in `paths`, its score and the matched characters.
+struct Candidate {
+    index: usize,
+    score: i64,
+    positions: Vec<usize>,
+}
+
+/// Fuzzy search over the paths below a directory, shown on top of everything else.
+pub struct Finder {
+    root: PathBuf,
+    walk: Walk,
+    paths: Vec<String>,
+    pub input: LineEdit,
+    // best first
+    matches: Vec<Candidate>,
+    selected_index: usize,
+}
+
+impl Finder {
+    pub fn new(root: &Path, show_hidden: bool) -> Self {
+        Finder {
+            root: root.to_path_buf(),
+            walk: Walk::start(root, show_hidden),
+            paths: Vec::new(),
+            input: LineEdit::default(),
+            matches: Vec::new(),
+            selected_index: 0,
+        }
+    }
+
+    fn score(&mut self, from: usize) {
+        let pattern = self.input.text();
+        let found = self.paths[from..]
+            .iter()
+            .enumerate()
+            .filter_map(|(offset, path)| {
+                finder::fuzzy_match(pattern, path).map(|found| Candidate {
+                    index: from + offset,
+                    score: found.score,
+                    positions: found.positions,
+                })
+            });
+        self.matches.extend(found);
+
+        let paths = &self.paths;
+        // shorter paths win ties, the walk order decides between equally long ones
+        self.matches.sort_by_key(|candidate| {
+            (
+                std::cmp::Reverse(candidate.score),
+                paths[candidate.index].len(),
+                candidate.index,
+            )
+        });
+    }
+
+    /// Takes in what the walk found since the last call, returns whether there is anything
+    /// new to show: the walk still running shows its progress.
+    pub fn update(&mut self) -> bool {
+        if self.walk.is_done() {
+            return false;
+        }
+        let from = self.paths.len();
+        let found = self.walk.poll();
+        if !found.is_empty() {
+            self.paths
+                .extend(found.iter().map(|path| path.display().to_string()));
+            self.score(from);
+        }
+        true
+    }
+
+    pub fn handle_key(&mut self, key: Key) {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Up => self.selected_index = self.selected_index.saturating_sub(1),
+            KeyCode::Char('p' | 'k') if control => {
+                self.selected_index = self.selected_index.saturating_sub(1)
+            }
+            KeyCode::Down => self.select_next(),
+            KeyCode::Char('n' | 'j') if control => self.select_next(),
+            _ => {
+                if self.input.handle_key(key) {
+                    self.matches.clear();
+                    self.selected_index = 0;
+                    self.score(0);
+                }
+            }
+        }
+    }
+
+    fn select_next(&mut self) {
+        self.selected_index = (self.selected_index + 1).min(self.matches.len().saturating_sub(1));
+    }
+
+    /// The absolute path of the selected match.
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        self.matches
+            .get(self.selected_index)
+            .map(|candidate| self.root.join(&self.paths[candidate.index]))
+    }
+
+    pub fn draw<B: tui::backend::Backend>(
+        &self,
+        f: &mut tui::Frame<B>,
+        area: Rect,
+        highlight_style: Style,
+    ) {
+        let area = centered(area, OVERLAY_PERCENT);
+        let title = format!(
+            "Find in {} ({}/{}{})",
+            self.root.display(),
+            self.matches.len(),
+            self.paths.len(),
+            if self.walk.is_done() {
+                ""
+            } else {
+                ", searching"
+            }
+        );
+        let block = Block::default().borders(Borders::ALL).title(title);
+        let chunks = Layout::default()
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(block.inner(area));
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+
+        let prompt_style = Style::default().fg(Color::LightYellow);
+        f.render_widget(
+            Paragraph::new(self.input.spans(String::from("> "), prompt_style)),
+            chunks[0],
+        );
+
+        // only what fits is turned into list items
+        let height = chunks[1].height as usize;
+        let offset = self.selected_index.saturating_sub(height.saturating_sub(1));
+        let match_style = Style::default()
+            .fg(Color::LightYellow)
+            .add_modifier(Modifier::BOLD);
+        let items: Vec<_> = self
+            .matches
+            .iter()
+            .skip(offset)
+            .take(height)
+            .map(|candidate| {
+                let path = &self.paths[candidate.index];
+                let spans: Vec<_> = path
+                    .chars()
+                    .enumerate()
+                    .map(|(index, c)| {
+                        if candidate.positions.contains(&index) {
+                            Span::styled(c.to_string(), match_style)
+                        } else {
+                            Span::raw(c.to_string())
+                        }
+                    })
+                    .collect();
+                ListItem::new(Spans::from(spans))
+            })
+            .collect();
+
+        let mut state = ListState::default();
+        if !items.is_empty() {
+            state.select(Some(self.selected_index - offset));
+        }
+        let list = List::new(items).highlight_style(highlight_style);
+        f.render_stateful_widget(list, chunks[1], &mut state);
+    }
+}
+
+/// A rectangle of `percent` of the size of `area`, in its middle.
+fn centered(area: Rect, percent: u16) -> Rect {
+    let width = area.width * percent / 100;
+    let height = area.height * percent / 100;
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
@@ -0,0 +1,95 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use tui::style::*;
+use tui::text::{Span, Spans};
+
+use crate::config::keys::Key;
+
+/// A single line of text being typed.
+#[derive(Debug, Default)]
+pub struct LineEdit {
+    text: String,
+    // byte offset into `text`
+    cursor: usize,
+}
+
+impl LineEdit {
+    pub fn new(text: &str) -> Self {
+        LineEdit {
+            text: text.to_string(),
+            cursor: text.len(),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn previous_boundary(&self) -> usize {
+        self.text[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(index, _)| index)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.text[self.cursor..]
+            .chars()
+            .next()
+            .map_or(self.cursor, |c| self.cursor + c.len_utf8())
+    }
+
+    /// Applies an editing key and returns whether the text changed.
+    pub fn handle_key(&mut self, key: Key) -> bool {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let length = self.text.len();
+        match key.code {
+            KeyCode::Char('u') if control => {
+                self.text.replace_range(..self.cursor, "");
+                self.cursor = 0;
+            }
+            KeyCode::Char('w') if control => {
+                let before = self.text[..self.cursor].trim_end();
+                let start = before.rfind(' ').map_or(0, |index| index + 1);
+                self.text.replace_range(start..self.cursor, "");
+                self.cursor = start;
+            }
+            KeyCode::Char('a') if control => self.cursor = 0,
+            KeyCode::Char('e') if control => self.cursor = self.text.len(),
+            KeyCode::Char(c) if !control => {
+                self.text.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                let start = self.previous_boundary();
+                self.text.replace_range(start..self.cursor, "");
+                self.cursor = start;
+            }
+            KeyCode::Delete if self.cursor < self.text.len() => {
+                let end = self.next_boundary();
+                self.text.replace_range(self.cursor..end, "");
+            }
+            KeyCode::Left => self.cursor = self.previous_boundary(),
+            KeyCode::Right => self.cursor = self.next_boundary(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.len(),
+            _ => {}
+        }
+        // every edit changes the length
+        self.text.len() != length
+    }
+
+    /// The text after `prompt`, with the cursor drawn as a reversed character.
+    pub fn spans(&self, prompt: String, style: Style) -> Spans<'_> {
+        let (before, rest) = self.text.split_at(self.cursor);
+        let (under_cursor, after) = match rest.chars().next() {
+            Some(c) => rest.split_at(c.len_utf8()),
+            None => (" ", ""),
+        };
+        Spans::from(vec![
+            Span::styled(prompt, style),
+            Span::styled(before, style),
+            Span::styled(under_cursor, style.add_modifier(Modifier::REVERSED)),
+            Span::styled(after, style),
+        ])
+    }
+}
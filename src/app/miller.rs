@@ -0,0 +1,148 @@
+use std::path::{Path, PathBuf};
+
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::*;
+use tui::widgets::*;
+
+use crate::config::miller::MillerOptions;
+use crate::config::sort::SortOptions;
+use crate::config::ViewOptions;
+use crate::model::{self, Item};
+use crate::navigation;
+
+/// The areas of the parent, current and preview columns, followed by what is left.
+pub fn columns(area: Rect, options: &MillerOptions) -> Vec<Rect> {
+    let widths = model::split_width(
+        area.width,
+        &[
+            (options.parent.width, options.parent.is_fixed_width),
+            (options.current.width, options.current.is_fixed_width),
+            (options.preview.width, options.preview.is_fixed_width),
+        ],
+    );
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length(widths[0]),
+            Constraint::Length(widths[1]),
+            Constraint::Length(widths[2]),
+            // the last constraint is stretched to the edge, fixed widths leave the rest blank
+            Constraint::Min(0),
+        ])
+        .split(area)
+}
+
+/// The listing of the parent directory in the Miller layout, read again when the
+/// current directory or the way entries are listed changes.
+pub struct ParentListing {
+    // what the items were read for: the parent directory, hidden entries and sorting
+    key: Option<(PathBuf, bool, SortOptions)>,
+    items: Vec<Item>,
+}
+
+impl ParentListing {
+    pub fn new() -> Self {
+        ParentListing {
+            key: None,
+            items: Vec::new(),
+        }
+    }
+
+    /// Reads the parent of `cwd` again if needed, returns whether it was read.
+    pub fn update(&mut self, cwd: &Path, options: &ViewOptions) -> bool {
+        let parent = match cwd.parent() {
+            Some(parent) => parent,
+            None => {
+                let was_shown = self.key.take().is_some();
+                self.items.clear();
+                return was_shown;
+            }
+        };
+        let key = (
+            parent.to_path_buf(),
+            options.show_hidden,
+            options.sort.clone(),
+        );
+        if self.key.as_ref() == Some(&key) {
+            return false;
+        }
+        // an unreadable parent is shown empty, unreadable entries are left out
+        self.items = navigation::read_items(parent, options, &mut Vec::new()).unwrap_or_default();
+        self.key = Some(key);
+        true
+    }
+
+    /// Forgets the items, so that the next update reads them again.
+    pub fn invalidate(&mut self) {
+        self.key = None;
+    }
+
+    /// Draws the parent directory with the current one selected.
+    pub fn draw<B: tui::backend::Backend>(
+        &self,
+        f: &mut tui::Frame<B>,
+        area: Rect,
+        cwd: &Path,
+        highlight_style: Style,
+    ) {
+        let name = cwd.file_name().map(|name| name.to_string_lossy());
+        let mut state = ListState::default();
+        state.select(
+            self.items
+                .iter()
+                .position(|item| Some(item.name.as_str()) == name.as_deref()),
+        );
+
+        let list_items: Vec<_> = self
+            .items
+            .iter()
+            .map(|item| ListItem::new(item.name.as_str()).style(model::item_style(item)))
+            .collect();
+        let title = match cwd.parent() {
+            Some(parent) => parent.display().to_string(),
+            None => String::new(),
+        };
+        let list = List::new(list_items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(highlight_style);
+        f.render_stateful_widget(list, area, &mut state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::miller::ColumnWidth;
+
+    #[test]
+    fn column_widths() {
+        let area = Rect::new(0, 0, 80, 10);
+        let widths = |options: &MillerOptions| -> Vec<u16> {
+            columns(area, options)[..3]
+                .iter()
+                .map(|area| area.width)
+                .collect()
+        };
+
+        assert_eq!(widths(&MillerOptions::default()), [10, 30, 40]);
+
+        let fixed = ColumnWidth {
+            width: 20,
+            is_fixed_width: true,
+        };
+        let options = MillerOptions {
+            parent: ColumnWidth::relative(1),
+            current: ColumnWidth::relative(1),
+            preview: fixed,
+        };
+        assert_eq!(widths(&options), [30, 30, 20]);
+
+        let options = MillerOptions {
+            parent: fixed,
+            current: fixed,
+            preview: fixed,
+        };
+        // the preview keeps its width instead of taking the rest
+        assert_eq!(widths(&options), [20, 20, 20]);
+    }
+}
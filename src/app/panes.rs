@@ -0,0 +1,36 @@
+use crate::config::sort::SortOptions;
+use crate::navigation::Navigator;
+
+use super::tabs::Tab;
+
+/// The second listing of the dual-pane layout. The focused pane is the navigator and
+/// sort options of the app itself, like the active tab.
+pub struct Panes {
+    pub other: Tab,
+    left_focused: bool,
+}
+
+impl Panes {
+    pub fn new(other: Tab) -> Self {
+        Panes {
+            other,
+            left_focused: true,
+        }
+    }
+
+    pub fn left_focused(&self) -> bool {
+        self.left_focused
+    }
+
+    /// Moves the focus to the other pane.
+    pub fn switch(&mut self, navigator: &mut Navigator, sort: &mut SortOptions) {
+        self.swap(navigator, sort);
+        self.left_focused ^= true;
+    }
+
+    /// Exchanges the directories of the panes, the focus stays on the same side.
+    pub fn swap(&mut self, navigator: &mut Navigator, sort: &mut SortOptions) {
+        std::mem::swap(navigator, &mut self.other.navigator);
+        std::mem::swap(sort, &mut self.other.sort);
+    }
+}
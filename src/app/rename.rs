@@ -0,0 +1,180 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crossterm::event::KeyCode;
+use tui::layout::{Constraint, Rect};
+use tui::style::*;
+use tui::text::Span;
+use tui::widgets::*;
+
+use super::input::LineEdit;
+use crate::config::keys::Key;
+use crate::model::Item;
+use crate::rename::{self, Pattern};
+
+const DEFAULT_TEMPLATE: &str = "{name}{ext}";
+
+pub fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Shows old and new names side by side, unchanged ones dimmed.
+pub fn draw_preview<B: tui::backend::Backend>(
+    f: &mut tui::Frame<B>,
+    area: Rect,
+    title: Span,
+    names: &[(String, String)],
+) {
+    let rows: Vec<_> = names
+        .iter()
+        .map(|(old, new)| {
+            let row = Row::new(vec![old.as_str(), new.as_str()]);
+            if old == new {
+                row.style(Style::default().fg(Color::DarkGray))
+            } else {
+                row
+            }
+        })
+        .collect();
+
+    let widths = [Constraint::Percentage(50), Constraint::Percentage(50)];
+    let table = Table::new(rows)
+        .header(
+            Row::new(vec!["Before", "After"]).style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .widths(&widths);
+    f.render_widget(table, area);
+}
+
+/// Renames entries after a regular expression or a template, previewing the new names
+/// while they are typed.
+pub struct RenameDialog {
+    // paths and modification times of the entries to rename
+    entries: Vec<(PathBuf, Option<SystemTime>)>,
+    pub input: LineEdit,
+    pub use_regex: bool,
+    names: Vec<(String, String)>,
+    // the checked renames, or why they cannot be done
+    renames: Result<Vec<(PathBuf, PathBuf)>, String>,
+}
+
+impl RenameDialog {
+    pub fn new(items: &[&Item]) -> Self {
+        let entries = items
+            .iter()
+            .map(|item| {
+                let modified = item
+                    .metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata.modified().ok());
+                (item.path.clone(), modified)
+            })
+            .collect();
+        let mut dialog = RenameDialog {
+            entries,
+            input: LineEdit::new(DEFAULT_TEMPLATE),
+            use_regex: false,
+            names: Vec::new(),
+            renames: Ok(Vec::new()),
+        };
+        dialog.update();
+        dialog
+    }
+
+    fn update(&mut self) {
+        let pattern = if self.use_regex {
+            Pattern::regex(self.input.text())
+        } else {
+            Pattern::template(self.input.text())
+        };
+        let pattern = match pattern {
+            Ok(pattern) => pattern,
+            // keep the last good preview while the pattern is incomplete
+            Err(error) => {
+                self.renames = Err(error);
+                return;
+            }
+        };
+
+        self.names = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(index, (path, modified))| {
+                let name = file_name(path);
+                let new_name = pattern.apply(&name, index, *modified);
+                (name, new_name)
+            })
+            .collect();
+        let renames: Vec<_> = self
+            .entries
+            .iter()
+            .zip(&self.names)
+            .map(|((path, _), (_, new_name))| (path.clone(), new_name.clone()))
+            .collect();
+        self.renames = rename::prepare(&renames);
+    }
+
+    pub fn handle_key(&mut self, key: Key) {
+        if key.code == KeyCode::Tab {
+            self.use_regex ^= true;
+            self.input = LineEdit::new(if self.use_regex { "" } else { DEFAULT_TEMPLATE });
+            self.update();
+        } else if self.input.handle_key(key) {
+            self.update();
+        }
+    }
+
+    pub fn renames(&self) -> &Result<Vec<(PathBuf, PathBuf)>, String> {
+        &self.renames
+    }
+
+    pub fn prompt(&self) -> String {
+        if self.use_regex {
+            String::from("Rename (regex/replacement, <Tab> for a template): ")
+        } else {
+            String::from("Rename ({name} {ext} {n:03} {mtime:%Y%m%d}, <Tab> for a regex): ")
+        }
+    }
+
+    pub fn draw<B: tui::backend::Backend>(&self, f: &mut tui::Frame<B>, area: Rect) {
+        let title = match &self.renames {
+            Ok(renames) => Span::raw(format!(
+                "Rename {} of {}",
+                renames.len(),
+                self.entries.len()
+            )),
+            Err(error) => Span::styled(error.as_str(), Style::default().fg(Color::LightRed)),
+        };
+        draw_preview(f, area, title, &self.names);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::ffi::OsStrExt;
+
+    #[test]
+    fn names_not_in_utf8() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("a.txt"), "").unwrap();
+        std::fs::write(root.join(std::ffi::OsStr::from_bytes(b"bad\xff.txt")), "").unwrap();
+        let options = crate::config::ViewOptions::default();
+        let items = crate::navigation::read_items(root, &options, &mut Vec::new()).unwrap();
+        let items: Vec<_> = items.iter().collect();
+
+        // shown unchanged, and left alone
+        let mut dialog = RenameDialog::new(&items);
+        assert_eq!(dialog.renames(), &Ok(Vec::new()));
+
+        dialog.input = LineEdit::new("{name}_1{ext}");
+        dialog.update();
+        assert!(dialog.renames().is_err());
+    }
+}
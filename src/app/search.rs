@@ -0,0 +1,121 @@
+use std::path::{Path, PathBuf};
+
+use tui::layout::{Constraint, Rect};
+use tui::style::*;
+use tui::text::{Span, Spans};
+use tui::widgets::*;
+
+use crate::search::{Hit, Search};
+
+/// Lines matching a search, listed while it is still running.
+pub struct SearchView {
+    root: PathBuf,
+    pattern: String,
+    pub search: Search,
+    pub hits: Vec<Hit>,
+    pub selected_index: usize,
+}
+
+impl SearchView {
+    pub fn new(root: &Path, pattern: &str, search: Search) -> Self {
+        SearchView {
+            root: root.to_path_buf(),
+            pattern: pattern.to_string(),
+            search,
+            hits: Vec::new(),
+            selected_index: 0,
+        }
+    }
+
+    /// Takes in the hits found since the last call, returns whether there is anything new
+    /// to show: the search still running shows its progress.
+    pub fn update(&mut self) -> bool {
+        let is_running = !self.search.is_done();
+        let hits = self.search.poll();
+        self.hits.extend(hits);
+        is_running
+    }
+
+    pub fn select_index(&mut self, index: usize) {
+        self.selected_index = index.min(self.hits.len().saturating_sub(1));
+    }
+
+    /// The absolute path and line number of the selected hit.
+    pub fn selected(&self) -> Option<(PathBuf, usize)> {
+        self.hits
+            .get(self.selected_index)
+            .map(|hit| (self.root.join(&hit.path), hit.line_number))
+    }
+
+    fn highlight_matches<'a>(&self, line: &'a str) -> Spans<'a> {
+        let match_style = Style::default()
+            .fg(Color::LightYellow)
+            .add_modifier(Modifier::BOLD);
+        let mut spans = Vec::new();
+        let mut end = 0;
+        for found in self.search.regex.find_iter(line) {
+            spans.push(Span::raw(&line[end..found.start()]));
+            spans.push(Span::styled(found.as_str(), match_style));
+            end = found.end();
+        }
+        spans.push(Span::raw(&line[end..]));
+        Spans::from(spans)
+    }
+
+    pub fn draw<B: tui::backend::Backend>(
+        &self,
+        f: &mut tui::Frame<B>,
+        area: Rect,
+        highlight_style: Style,
+    ) {
+        // only what fits is turned into rows, without the borders
+        let height = area.height.saturating_sub(2) as usize;
+        let offset = self.selected_index.saturating_sub(height.saturating_sub(1));
+        let rows: Vec<_> = self
+            .hits
+            .iter()
+            .skip(offset)
+            .take(height)
+            .map(|hit| {
+                Row::new(vec![
+                    Cell::from(hit.path.display().to_string()),
+                    Cell::from(hit.line_number.to_string()),
+                    Cell::from(self.highlight_matches(&hit.line)),
+                ])
+            })
+            .collect();
+
+        let mut state = TableState::default();
+        if !rows.is_empty() {
+            state.select(Some(self.selected_index - offset));
+        }
+
+        let status = if self.search.is_cancelled() {
+            ", stopped"
+        } else if !self.search.is_done() {
+            ", searching"
+        } else {
+            ""
+        };
+        let title = format!(
+            "Search '{}' in {} ({} in {} files{status})",
+            self.pattern,
+            self.root.display(),
+            match self.hits.len() {
+                1 => String::from("1 line"),
+                n => format!("{n} lines"),
+            },
+            self.search.files_searched(),
+        );
+        let widths = [
+            Constraint::Percentage(30),
+            Constraint::Length(6),
+            Constraint::Percentage(70),
+        ];
+        let table = Table::new(rows)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .widths(&widths)
+            .highlight_style(highlight_style);
+        f.render_stateful_widget(table, area, &mut state);
+    }
+}
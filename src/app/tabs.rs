@@ -0,0 +1,145 @@
+use std::path::Path;
+
+use tui::layout::Rect;
+use tui::style::*;
+use tui::text::Spans;
+
+use crate::config::sort::SortOptions;
+use crate::config::ViewOptions;
+use crate::navigation::Navigator;
+
+/// What a tab keeps while another one is shown.
+pub struct Tab {
+    pub navigator: Navigator,
+    pub sort: SortOptions,
+}
+
+impl Tab {
+    /// Re-reads the directory with the sort options of the tab.
+    pub fn reload(&mut self, options: &ViewOptions) -> std::io::Result<()> {
+        let options = ViewOptions {
+            sort: self.sort.clone(),
+            ..options.clone()
+        };
+        self.navigator.reload(&options)
+    }
+}
+
+/// The tabs in the background. The active tab is the navigator and sort options of
+/// the app itself, they are swapped with a background tab when switching.
+pub struct Tabs {
+    // in order, without the active one
+    inactive: Vec<Tab>,
+    active: usize,
+}
+
+impl Tabs {
+    pub fn new() -> Self {
+        Tabs {
+            inactive: Vec::new(),
+            active: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inactive.len() + 1
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// Shows a new tab right after the active one.
+    pub fn open(&mut self, mut tab: Tab, navigator: &mut Navigator, sort: &mut SortOptions) {
+        std::mem::swap(navigator, &mut tab.navigator);
+        std::mem::swap(sort, &mut tab.sort);
+        self.inactive.insert(self.active, tab);
+        self.active += 1;
+    }
+
+    /// Replaces the active tab with the next one, or the previous one if it is the last.
+    /// Returns `false` if it is the only tab.
+    pub fn close(&mut self, navigator: &mut Navigator, sort: &mut SortOptions) -> bool {
+        if self.inactive.is_empty() {
+            return false;
+        }
+        let position = self.active.min(self.inactive.len() - 1);
+        let tab = self.inactive.remove(position);
+        *navigator = tab.navigator;
+        *sort = tab.sort;
+        self.active = position;
+        true
+    }
+
+    /// Makes the tab at `index` the active one, returns `false` if it already is or
+    /// there is no such tab.
+    pub fn switch(
+        &mut self,
+        index: usize,
+        navigator: &mut Navigator,
+        sort: &mut SortOptions,
+    ) -> bool {
+        if index >= self.len() || index == self.active {
+            return false;
+        }
+        // positions within the inactive tabs, where the active one is missing
+        let position = if index < self.active {
+            index
+        } else {
+            index - 1
+        };
+        let mut tab = self.inactive.remove(position);
+        std::mem::swap(navigator, &mut tab.navigator);
+        std::mem::swap(sort, &mut tab.sort);
+        let position = if self.active < index {
+            self.active
+        } else {
+            self.active - 1
+        };
+        self.inactive.insert(position, tab);
+        self.active = index;
+        true
+    }
+
+    /// Moves the active tab by `offset` places, stopping at either end.
+    pub fn move_active(&mut self, offset: isize) {
+        self.active = self
+            .active
+            .saturating_add_signed(offset)
+            .min(self.len() - 1);
+    }
+
+    /// One line with the number and the directory name of every tab.
+    pub fn draw<B: tui::backend::Backend>(
+        &self,
+        f: &mut tui::Frame<B>,
+        area: Rect,
+        active_dir: &Path,
+        highlight_style: Style,
+    ) {
+        let mut dirs: Vec<&Path> = self
+            .inactive
+            .iter()
+            .map(|tab| tab.navigator.cwd())
+            .collect();
+        dirs.insert(self.active, active_dir);
+        // numbered like the actions switching to them
+        let titles: Vec<Spans> = dirs
+            .iter()
+            .enumerate()
+            .map(|(index, dir)| Spans::from(format!("{} {}", index + 1, label(dir))))
+            .collect();
+
+        let tabs = tui::widgets::Tabs::new(titles)
+            .select(self.active)
+            .highlight_style(highlight_style);
+        f.render_widget(tabs, area);
+    }
+}
+
+fn label(dir: &Path) -> String {
+    match dir.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => dir.display().to_string(),
+    }
+}
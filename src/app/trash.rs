@@ -0,0 +1,76 @@
+use tui::layout::{Constraint, Rect};
+use tui::style::*;
+use tui::widgets::*;
+
+use crate::config::column::DEFAULT_TIME_FORMAT;
+use crate::trash::{self, TrashedItem};
+
+/// Entries of all trash directories, most recently deleted first.
+pub struct TrashView {
+    pub items: Vec<TrashedItem>,
+    pub selected_index: usize,
+}
+
+impl TrashView {
+    pub fn new() -> Self {
+        TrashView {
+            items: trash::items(),
+            selected_index: 0,
+        }
+    }
+
+    pub fn reload(&mut self) {
+        self.items = trash::items();
+        self.select_index(self.selected_index);
+    }
+
+    pub fn select_index(&mut self, index: usize) {
+        self.selected_index = index.min(self.items.len().saturating_sub(1));
+    }
+
+    pub fn selected_item(&self) -> Option<&TrashedItem> {
+        self.items.get(self.selected_index)
+    }
+
+    pub fn draw<B: tui::backend::Backend>(
+        &self,
+        f: &mut tui::Frame<B>,
+        area: Rect,
+        highlight_style: Style,
+    ) {
+        let rows: Vec<_> = self
+            .items
+            .iter()
+            .map(|item| {
+                let mut name = item.name.to_string_lossy().to_string();
+                if item.path().is_dir() {
+                    name.push('/');
+                }
+                let date = item
+                    .deletion_date
+                    .map(|date| date.format(DEFAULT_TIME_FORMAT).to_string())
+                    .unwrap_or_default();
+                Row::new(vec![name, item.original_path.display().to_string(), date])
+            })
+            .collect();
+
+        let mut state = TableState::default();
+        state.select(Some(self.selected_index));
+
+        let title = format!("Trash ({})", self.items.len());
+        let widths = [
+            Constraint::Percentage(30),
+            Constraint::Percentage(50),
+            Constraint::Min(16),
+        ];
+        let table = Table::new(rows)
+            .header(
+                Row::new(vec!["Name", "Original path", "Deleted"])
+                    .style(Style::default().add_modifier(Modifier::BOLD)),
+            )
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .widths(&widths)
+            .highlight_style(highlight_style);
+        f.render_stateful_widget(table, area, &mut state);
+    }
+}
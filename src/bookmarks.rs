@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::path_codec::{percent_decode, percent_encode};
+
+const BOOKMARKS_FILE: &str = "bookmarks";
+
+/// Directories reachable by a single key. Bookmarks set while running are saved and
+/// take precedence over the ones declared in `rsfm.bookmarks`.
+pub struct Bookmarks {
+    declared: BTreeMap<char, PathBuf>,
+    saved: BTreeMap<char, PathBuf>,
+    // where the saved bookmarks are kept between sessions
+    path: Option<PathBuf>,
+}
+
+impl Bookmarks {
+    pub fn new(declared: &BTreeMap<char, String>) -> Self {
+        Bookmarks::open(
+            declared,
+            config::state_dir().map(|dir| dir.join(BOOKMARKS_FILE)),
+        )
+    }
+
+    /// Bookmarks loaded from and saved to `path` if given.
+    pub fn open(declared: &BTreeMap<char, String>, path: Option<PathBuf>) -> Self {
+        let declared = declared
+            .iter()
+            .map(|(key, path)| (*key, config::expand_home(path)))
+            .collect();
+        let saved = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|contents| parse(&contents))
+            .unwrap_or_default();
+        Bookmarks {
+            declared,
+            saved,
+            path,
+        }
+    }
+
+    pub fn get(&self, key: char) -> Option<&Path> {
+        self.saved
+            .get(&key)
+            .or_else(|| self.declared.get(&key))
+            .map(PathBuf::as_path)
+    }
+
+    pub fn set(&mut self, key: char, path: &Path) {
+        self.saved.insert(key, path.to_path_buf());
+        self.save();
+    }
+
+    /// Forgets a saved bookmark, returns `false` if there is none. A declared bookmark
+    /// with the same key becomes visible again.
+    pub fn remove(&mut self, key: char) -> bool {
+        let removed = self.saved.remove(&key).is_some();
+        if removed {
+            self.save();
+        }
+        removed
+    }
+
+    /// All bookmarks by key, and whether each one comes from the configuration.
+    pub fn list(&self) -> Vec<(char, &Path, bool)> {
+        let mut keys: Vec<char> = self
+            .declared
+            .keys()
+            .chain(self.saved.keys())
+            .copied()
+            .collect();
+        keys.sort_unstable();
+        keys.dedup();
+        keys.into_iter()
+            .filter_map(|key| {
+                let is_declared = !self.saved.contains_key(&key);
+                self.get(key).map(|path| (key, path, is_declared))
+            })
+            .collect()
+    }
+
+    fn save(&self) {
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            // losing the bookmarks is not worth interrupting the user for
+            let _ = fs::write(path, serialize(&self.saved));
+        }
+    }
+}
+
+/// One line per bookmark: the key and the percent-encoded path.
+fn serialize(bookmarks: &BTreeMap<char, PathBuf>) -> String {
+    bookmarks
+        .iter()
+        .map(|(key, path)| format!("{key} {}\n", percent_encode(path)))
+        .collect()
+}
+
+fn parse(contents: &str) -> BTreeMap<char, PathBuf> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut chars = line.chars();
+            match (chars.next(), chars.next(), chars.as_str()) {
+                (Some(key), Some(' '), path) if !path.is_empty() => {
+                    Some((key, percent_decode(path)))
+                }
+                // skip what cannot be read rather than refusing the whole file
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_and_declared() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let path = root.join("state/bookmarks");
+        let declared =
+            BTreeMap::from([('d', String::from("/declared")), ('e', String::from("/e"))]);
+
+        let mut bookmarks = Bookmarks::open(&declared, Some(path.clone()));
+        assert_eq!(bookmarks.get('d'), Some(Path::new("/declared")));
+        bookmarks.set('d', Path::new("/saved dir"));
+        bookmarks.set(' ', Path::new("/space"));
+        assert_eq!(bookmarks.get('d'), Some(Path::new("/saved dir")));
+
+        let mut loaded = Bookmarks::open(&declared, Some(path));
+        assert_eq!(
+            loaded.list(),
+            vec![
+                (' ', Path::new("/space"), false),
+                ('d', Path::new("/saved dir"), false),
+                ('e', Path::new("/e"), true),
+            ]
+        );
+
+        assert!(loaded.remove('d'));
+        assert!(!loaded.remove('e'));
+        assert_eq!(loaded.get('d'), Some(Path::new("/declared")));
+        assert_eq!(loaded.get('x'), None);
+    }
+}
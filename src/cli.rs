@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+
+/// Terminal file manager
+#[derive(clap::Parser, Debug, Default)]
+#[command(version, about)]
+pub struct Args {
+    /// Directory to open, or a file to select inside its directory
+    pub path: Option<PathBuf>,
+
+    /// Read configuration from this file
+    #[arg(short, long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Show hidden files regardless of the configuration
+    #[arg(short = 'a', long)]
+    pub show_hidden: bool,
+}
+
+impl Args {
+    /// Overrides configuration values with the ones given on the command line.
+    pub fn apply(&self, options: &mut crate::ViewOptions) {
+        if self.show_hidden {
+            options.show_hidden = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Args;
+    use clap::Parser;
+
+    #[test]
+    fn parse_args() {
+        let args = Args::parse_from(["rsfm", "--config", "my.lua", "-a", "/tmp"]);
+        assert_eq!(args.path, Some("/tmp".into()));
+        assert_eq!(args.config, Some("my.lua".into()));
+        assert!(args.show_hidden);
+
+        let args = Args::parse_from(["rsfm"]);
+        assert_eq!(args.path, None);
+        assert_eq!(args.config, None);
+        assert!(!args.show_hidden);
+    }
+
+    #[test]
+    fn apply_show_hidden() {
+        let mut options = crate::ViewOptions::default();
+        Args::default().apply(&mut options);
+        assert!(!options.show_hidden);
+
+        Args::parse_from(["rsfm", "--show-hidden"]).apply(&mut options);
+        assert!(options.show_hidden);
+    }
+}
@@ -1,25 +1,58 @@
+use std::path::{Path, PathBuf};
+
+mod error;
+pub mod keys;
 pub mod parser;
 
+pub use error::{ConfigError, ConfigWarning};
+
 pub mod column {
-    #[derive(Debug, PartialEq)]
+    pub const DEFAULT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+    #[derive(Debug, Clone, PartialEq)]
     pub enum ColumnType {
         Name,
         Size,
         Permissions,
+        Modified { format: String },
+        Accessed { format: String },
+        Created { format: String },
+        Owner { numeric: bool },
+        Group { numeric: bool },
+        Inode,
+        Links,
+        Extension,
+        MimeType,
     }
 
     impl ColumnType {
+        #[allow(clippy::result_unit_err)]
         pub fn from(typename: &str) -> Result<Self, ()> {
             match typename.to_lowercase().as_str() {
                 "name" => Ok(ColumnType::Name),
                 "size" => Ok(ColumnType::Size),
                 "permissions" => Ok(ColumnType::Permissions),
+                "modified" => Ok(ColumnType::Modified {
+                    format: DEFAULT_TIME_FORMAT.to_string(),
+                }),
+                "accessed" => Ok(ColumnType::Accessed {
+                    format: DEFAULT_TIME_FORMAT.to_string(),
+                }),
+                "created" => Ok(ColumnType::Created {
+                    format: DEFAULT_TIME_FORMAT.to_string(),
+                }),
+                "owner" => Ok(ColumnType::Owner { numeric: false }),
+                "group" => Ok(ColumnType::Group { numeric: false }),
+                "inode" => Ok(ColumnType::Inode),
+                "links" => Ok(ColumnType::Links),
+                "extension" => Ok(ColumnType::Extension),
+                "mimetype" => Ok(ColumnType::MimeType),
                 _ => Err(()),
             }
         }
     }
 
-    #[derive(Debug, PartialEq)]
+    #[derive(Debug, Clone, PartialEq)]
     pub enum Alignment {
         Left,
         Center,
@@ -27,6 +60,7 @@ pub mod column {
     }
 
     impl Alignment {
+        #[allow(clippy::result_unit_err)]
         pub fn from(typename: &str) -> Result<Self, ()> {
             match typename.to_lowercase().as_str() {
                 "left" => Ok(Alignment::Left),
@@ -37,7 +71,7 @@ pub mod column {
         }
     }
 
-    #[derive(Debug, PartialEq)]
+    #[derive(Debug, Clone, PartialEq)]
     pub struct Column {
         pub column_type: ColumnType,
         pub width: u16,
@@ -45,6 +79,30 @@ pub mod column {
         pub alignment: Alignment,
     }
 
+    /// Columns shown when no configuration sets `rsfm.entry_format`.
+    pub fn default_entry_format() -> Vec<Column> {
+        vec![
+            Column {
+                column_type: ColumnType::Name,
+                width: 1,
+                is_fixed_width: false,
+                alignment: Alignment::Left,
+            },
+            Column {
+                column_type: ColumnType::Size,
+                width: 10,
+                is_fixed_width: true,
+                alignment: Alignment::Right,
+            },
+            Column {
+                column_type: ColumnType::Permissions,
+                width: 10,
+                is_fixed_width: true,
+                alignment: Alignment::Right,
+            },
+        ]
+    }
+
     impl Column {
         pub fn new(column_typename: &str, width: u16, is_fixed_width: bool) -> Self {
             Column {
@@ -58,59 +116,490 @@ pub mod column {
     }
 }
 
-#[derive(Debug)]
+pub mod sort {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum SortKey {
+        Name,
+        Size,
+        Modified,
+        Changed,
+        Extension,
+        Type,
+    }
+
+    impl SortKey {
+        const ALL: [SortKey; 6] = [
+            SortKey::Name,
+            SortKey::Size,
+            SortKey::Modified,
+            SortKey::Changed,
+            SortKey::Extension,
+            SortKey::Type,
+        ];
+
+        #[allow(clippy::result_unit_err)]
+        pub fn from(typename: &str) -> Result<Self, ()> {
+            match typename.to_lowercase().as_str() {
+                "name" => Ok(SortKey::Name),
+                "size" => Ok(SortKey::Size),
+                "mtime" => Ok(SortKey::Modified),
+                "ctime" => Ok(SortKey::Changed),
+                "extension" => Ok(SortKey::Extension),
+                "type" => Ok(SortKey::Type),
+                _ => Err(()),
+            }
+        }
+
+        pub fn name(&self) -> &'static str {
+            match self {
+                SortKey::Name => "name",
+                SortKey::Size => "size",
+                SortKey::Modified => "mtime",
+                SortKey::Changed => "ctime",
+                SortKey::Extension => "extension",
+                SortKey::Type => "type",
+            }
+        }
+
+        /// The key following this one, wrapping around, used to cycle the sort order.
+        pub fn next(&self) -> Self {
+            let index = SortKey::ALL.iter().position(|key| key == self).unwrap();
+            SortKey::ALL[(index + 1) % SortKey::ALL.len()]
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SortOptions {
+        pub by: SortKey,
+        pub reverse: bool,
+        pub dirs_first: bool,
+        pub natural: bool,
+        pub case_sensitive: bool,
+        /// Sort symlinks as the entries they point to.
+        pub follow_links: bool,
+    }
+
+    impl Default for SortOptions {
+        fn default() -> Self {
+            SortOptions {
+                by: SortKey::Name,
+                reverse: false,
+                dirs_first: true,
+                natural: true,
+                case_sensitive: true,
+                follow_links: false,
+            }
+        }
+    }
+}
+
+pub mod preview {
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct PreviewOptions {
+        pub enabled: bool,
+        /// Width of the preview pane in percent of the screen.
+        pub width: u16,
+        pub max_lines: usize,
+        /// Files are never read past this size.
+        pub max_bytes: u64,
+    }
+
+    impl Default for PreviewOptions {
+        fn default() -> Self {
+            PreviewOptions {
+                enabled: false,
+                width: 40,
+                max_lines: 200,
+                max_bytes: 64 * 1024,
+            }
+        }
+    }
+}
+
+pub mod journal {
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct JournalOptions {
+        /// Keep the journal between sessions, see [`super::state_dir`].
+        pub persist: bool,
+        /// How many operations can be undone.
+        pub size: usize,
+    }
+
+    impl Default for JournalOptions {
+        fn default() -> Self {
+            JournalOptions {
+                persist: false,
+                size: 100,
+            }
+        }
+    }
+}
+
+pub mod layout {
+    /// How directories are laid out on the screen.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum Layout {
+        #[default]
+        Single,
+        /// Two listings side by side, commander style.
+        Dual,
+        /// The parent directory, the current one and the preview of the selected entry.
+        Miller,
+    }
+
+    impl Layout {
+        const ALL: [Layout; 3] = [Layout::Single, Layout::Dual, Layout::Miller];
+
+        #[allow(clippy::result_unit_err)]
+        pub fn from(name: &str) -> Result<Self, ()> {
+            Layout::ALL
+                .iter()
+                .find(|layout| layout.name() == name.to_lowercase())
+                .copied()
+                .ok_or(())
+        }
+
+        pub fn name(&self) -> &'static str {
+            match self {
+                Layout::Single => "single",
+                Layout::Dual => "dual",
+                Layout::Miller => "miller",
+            }
+        }
+
+        /// The layout following this one, wrapping around.
+        pub fn next(&self) -> Self {
+            let index = Layout::ALL
+                .iter()
+                .position(|layout| layout == self)
+                .unwrap();
+            Layout::ALL[(index + 1) % Layout::ALL.len()]
+        }
+    }
+}
+
+pub mod miller {
+    /// Width of a column of the Miller layout, relative to the other columns unless fixed.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct ColumnWidth {
+        pub width: u16,
+        pub is_fixed_width: bool,
+    }
+
+    impl ColumnWidth {
+        pub const fn relative(width: u16) -> Self {
+            ColumnWidth {
+                width,
+                is_fixed_width: false,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct MillerOptions {
+        pub parent: ColumnWidth,
+        pub current: ColumnWidth,
+        pub preview: ColumnWidth,
+    }
+
+    impl Default for MillerOptions {
+        fn default() -> Self {
+            MillerOptions {
+                parent: ColumnWidth::relative(1),
+                current: ColumnWidth::relative(3),
+                preview: ColumnWidth::relative(4),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct ViewOptions {
     pub show_hidden: bool,
     pub entry_format: Vec<column::Column>,
+    pub sort: sort::SortOptions,
+    pub preview: preview::PreviewOptions,
+    pub journal: journal::JournalOptions,
+    pub layout: layout::Layout,
+    pub miller: miller::MillerOptions,
+    /// Bookmarks declared in the configuration, by key.
+    pub bookmarks: std::collections::BTreeMap<char, String>,
+    pub keys: keys::Keymap,
 }
 
-impl ViewOptions {
-    pub fn default() -> ViewOptions {
+impl Default for ViewOptions {
+    fn default() -> Self {
         ViewOptions {
             show_hidden: false,
-            entry_format: Vec::new(),
+            entry_format: column::default_entry_format(),
+            sort: sort::SortOptions::default(),
+            preview: preview::PreviewOptions::default(),
+            journal: journal::JournalOptions::default(),
+            layout: layout::Layout::default(),
+            miller: miller::MillerOptions::default(),
+            bookmarks: std::collections::BTreeMap::new(),
+            keys: keys::Keymap::default(),
         }
     }
 }
 
-pub fn read_config(path: &std::path::Path) -> ViewOptions {
-    let mut result = ViewOptions::default();
+const CONFIG_FILE: &str = "rsfm/config.lua";
+const STATE_DIR: &str = "rsfm";
+const DEFAULT_SYSTEM_CONFIG_DIRS: &str = "/etc/xdg";
 
-    match std::fs::read_to_string(path) {
-        Ok(config_source) => {
-            let lua = rlua::Lua::new();
+/// Lists configuration files in the order they should be loaded, so later files override
+/// earlier ones: system files from `$XDG_CONFIG_DIRS` first, then the user file.
+///
+/// The user file is `override_path` if given, otherwise `$RSFM_CONFIG`, otherwise the first
+/// existing of `$XDG_CONFIG_HOME/rsfm/config.lua` and `~/.config/rsfm/config.lua`.
+pub fn config_paths(override_path: Option<&Path>) -> Vec<PathBuf> {
+    config_paths_from(|name| std::env::var_os(name), override_path)
+}
 
-            match lua.context(|ctx| -> Result<(), rlua::Error> {
-                let rsfm = ctx.create_table()?;
-                let globals = ctx.globals();
+fn config_paths_from<F>(env: F, override_path: Option<&Path>) -> Vec<PathBuf>
+where
+    F: Fn(&str) -> Option<std::ffi::OsString>,
+{
+    let non_empty = |name: &str| env(name).filter(|value| !value.is_empty());
 
-                globals.set("rsfm", rsfm)?;
+    let system_dirs =
+        non_empty("XDG_CONFIG_DIRS").unwrap_or_else(|| DEFAULT_SYSTEM_CONFIG_DIRS.into());
+    let system_dirs: Vec<_> = std::env::split_paths(&system_dirs).collect();
 
-                ctx.load(&config_source).set_name("config")?.exec()?;
+    // XDG_CONFIG_DIRS is ordered by importance, so the least important file goes first
+    let mut paths: Vec<PathBuf> = system_dirs
+        .iter()
+        .rev()
+        .map(|dir| dir.join(CONFIG_FILE))
+        .filter(|path| path.is_file())
+        .collect();
+
+    let explicit = override_path
+        .map(Path::to_path_buf)
+        .or_else(|| non_empty("RSFM_CONFIG").map(PathBuf::from));
+
+    let user = match explicit {
+        Some(path) => Some(path),
+        None => {
+            let xdg_home = non_empty("XDG_CONFIG_HOME").map(PathBuf::from);
+            let home = non_empty("HOME").map(|home| PathBuf::from(home).join(".config"));
+            xdg_home
+                .into_iter()
+                .chain(home)
+                .map(|dir| dir.join(CONFIG_FILE))
+                .find(|path| path.is_file())
+        }
+    };
 
-                let rsfm = globals.get::<_, rlua::Value>("rsfm")?;
+    if let Some(user) = user {
+        if !paths.contains(&user) {
+            paths.push(user);
+        }
+    }
 
-                if let Err(errors) = parser::parse_syntax("rsfm", rsfm.clone()) {
-                    eprintln!("Configuration syntax error: ");
-                    for e in errors {
-                        eprintln!("{e}");
-                    }
-                };
+    paths
+}
 
-                result = parser::parse_values(rsfm)?;
+/// Replaces a leading `~` with the home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            PathBuf::from(home).join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
 
-                Ok(())
-            }) {
-                Ok(()) => result,
-                Err(error) => {
-                    eprintln!("Lua execution error: {error}");
-                    result
-                }
+/// Where files kept between sessions go: `$XDG_STATE_HOME/rsfm` or `~/.local/state/rsfm`.
+pub fn state_dir() -> Option<PathBuf> {
+    let state_home = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(state_home.join(STATE_DIR))
+}
+
+/// Executes configuration files one after another in a single Lua state, so that each file
+/// can override values set by the previous ones, then reads the resulting `rsfm` table.
+///
+/// Failing to read or execute a file is an error; invalid values are skipped and reported
+/// as warnings.
+///
+/// The files are executed in `lua`, which may outlive the call. An existing `rsfm` table,
+/// e.g. holding the scripting API, is reused.
+pub fn read_config(
+    lua: &rlua::Lua,
+    paths: &[PathBuf],
+) -> Result<(ViewOptions, Vec<ConfigWarning>), ConfigError> {
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        let has_rsfm = globals
+            .contains_key("rsfm")
+            .map_err(|error| ConfigError::from_lua(None, &error))?;
+        if !has_rsfm {
+            let rsfm = ctx
+                .create_table()
+                .map_err(|error| ConfigError::from_lua(None, &error))?;
+            globals
+                .set("rsfm", rsfm)
+                .map_err(|error| ConfigError::from_lua(None, &error))?;
+        }
+
+        for path in paths {
+            let config_source =
+                std::fs::read_to_string(path).map_err(|error| ConfigError::Read {
+                    path: path.clone(),
+                    message: error.to_string(),
+                })?;
+
+            ctx.load(&config_source)
+                .set_name(&format!("@{}", path.display()))
+                .and_then(|chunk| chunk.exec())
+                .map_err(|error| ConfigError::from_lua(Some(path), &error))?;
+        }
+
+        let rsfm = globals
+            .get::<_, rlua::Value>("rsfm")
+            .map_err(|error| ConfigError::from_lua(None, &error))?;
+
+        let mut warnings = parser::parse_syntax("rsfm", rsfm.clone())
+            .err()
+            .unwrap_or_default();
+        let (options, value_warnings) = parser::parse_values(rsfm)?;
+
+        // a mistyped value is reported by both the syntax check and the value parser
+        for warning in value_warnings {
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
             }
         }
-        Err(error) => {
-            eprintln!("Error reading configuration file: {error}");
-            result
+
+        Ok((options, warnings))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(dir: &Path, source: &str) -> PathBuf {
+        let path = dir.join(CONFIG_FILE);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, source).unwrap();
+        path
+    }
+
+    #[test]
+    fn config_paths_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let system_low = write_config(&root.join("system_low"), "");
+        let system_high = write_config(&root.join("system_high"), "");
+        let xdg_home = write_config(&root.join("xdg_home"), "");
+        let home = write_config(&root.join("home/.config"), "");
+
+        let dirs =
+            std::env::join_paths([root.join("system_high"), root.join("system_low")]).unwrap();
+        let env = |name: &str| match name {
+            "XDG_CONFIG_DIRS" => Some(dirs.clone()),
+            "XDG_CONFIG_HOME" => Some(root.join("xdg_home").into()),
+            "HOME" => Some(root.join("home").into()),
+            _ => None,
+        };
+        assert_eq!(
+            config_paths_from(env, None),
+            vec![system_low.clone(), system_high.clone(), xdg_home.clone()]
+        );
+
+        let env = |name: &str| match name {
+            "XDG_CONFIG_DIRS" => Some(dirs.clone()),
+            "HOME" => Some(root.join("home").into()),
+            _ => None,
+        };
+        assert_eq!(
+            config_paths_from(env, None),
+            vec![system_low.clone(), system_high.clone(), home]
+        );
+
+        let env = |name: &str| match name {
+            "RSFM_CONFIG" => Some("from_env.lua".into()),
+            "XDG_CONFIG_HOME" => Some(root.join("xdg_home").into()),
+            _ => None,
+        };
+        assert_eq!(
+            config_paths_from(env, None),
+            vec![PathBuf::from("from_env.lua")]
+        );
+        assert_eq!(
+            config_paths_from(env, Some(Path::new("from_cli.lua"))),
+            vec![PathBuf::from("from_cli.lua")]
+        );
+    }
+
+    #[test]
+    fn layered_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let system = write_config(
+            &root.join("system"),
+            r#"
+            rsfm.show_hidden = true
+            rsfm.entry_format = {
+                { type = "name", width = 1, is_fixed_width = false, alignment = "left" },
+            }
+            "#,
+        );
+        let user = write_config(&root.join("user"), "rsfm.show_hidden = false");
+
+        let (options, warnings) =
+            read_config(&rlua::Lua::new(), std::slice::from_ref(&system)).unwrap();
+        assert!(options.show_hidden);
+        assert_eq!(options.entry_format.len(), 1);
+        assert!(warnings.is_empty());
+
+        let (options, _) = read_config(&rlua::Lua::new(), &[system, user]).unwrap();
+        assert!(!options.show_hidden);
+        assert_eq!(options.entry_format.len(), 1);
+    }
+
+    #[test]
+    fn config_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let broken = write_config(root, "rsfm.show_hidden = true\nrsfm.x.y = 1\n");
+
+        match read_config(&rlua::Lua::new(), std::slice::from_ref(&broken)) {
+            Err(ConfigError::Lua { path, line, .. }) => {
+                assert_eq!(path, Some(broken));
+                assert_eq!(line, Some(2));
+            }
+            result => panic!("unexpected result: {result:?}"),
         }
+
+        let missing = root.join("missing.lua");
+        assert!(matches!(
+            read_config(&rlua::Lua::new(), &[missing]),
+            Err(ConfigError::Read { .. })
+        ));
+
+        let mistyped = write_config(
+            root,
+            "rsfm.show_hidden = 1\nrsfm.entry_format = {{ type = 'name' }}",
+        );
+        let (options, warnings) = read_config(&rlua::Lua::new(), &[mistyped]).unwrap();
+        assert!(!options.show_hidden);
+        assert_eq!(
+            warnings,
+            vec![
+                ConfigWarning(ConfigError::TypeMismatch {
+                    name: "rsfm.show_hidden".to_string(),
+                    expected: "boolean",
+                    actual: "integer",
+                }),
+                ConfigWarning(ConfigError::MissingField {
+                    name: "rsfm.entry_format.1.width".to_string(),
+                }),
+            ]
+        );
     }
 }
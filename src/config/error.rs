@@ -0,0 +1,165 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    UnknownVariable {
+        name: String,
+        suggestion: Option<String>,
+    },
+    TypeMismatch {
+        name: String,
+        expected: &'static str,
+        actual: &'static str,
+    },
+    MissingField {
+        name: String,
+    },
+    InvalidValue {
+        name: String,
+        value: String,
+    },
+    Read {
+        path: PathBuf,
+        message: String,
+    },
+    Lua {
+        path: Option<PathBuf>,
+        line: Option<u32>,
+        message: String,
+    },
+}
+
+impl ConfigError {
+    /// Converts an error raised while executing the Lua chunk loaded from `path`.
+    ///
+    /// Chunks are expected to be named `@<path>` so that Lua prefixes its messages with
+    /// `<path>:<line>:`, which is where the line number is taken from.
+    pub fn from_lua(path: Option<&Path>, error: &rlua::Error) -> Self {
+        let message = match error {
+            rlua::Error::SyntaxError { message, .. } => message.clone(),
+            rlua::Error::RuntimeError(message) => message.clone(),
+            rlua::Error::CallbackError { cause, .. } => return Self::from_lua(path, cause),
+            error => error.to_string(),
+        };
+        // drop the stack traceback
+        let message = message.lines().next().unwrap_or_default();
+
+        let located = path.and_then(|path| {
+            let rest = message.strip_prefix(&format!("{}:", path.display()))?;
+            let (line, rest) = rest.split_once(':')?;
+            Some((line.parse().ok()?, rest.trim_start()))
+        });
+
+        let (line, message) = match located {
+            Some((line, message)) => (Some(line), message),
+            None => (None, message),
+        };
+
+        ConfigError::Lua {
+            path: path.map(Path::to_path_buf),
+            line,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::UnknownVariable {
+                name,
+                suggestion: Some(suggestion),
+            } => write!(f, "Unknown variable '{name}'. Did you mean '{suggestion}'?"),
+            ConfigError::UnknownVariable {
+                name,
+                suggestion: None,
+            } => write!(f, "Unknown variable '{name}'"),
+            ConfigError::TypeMismatch {
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Unexpected type '{actual}' for variable '{name}', use '{expected}'"
+            ),
+            ConfigError::MissingField { name } => write!(f, "Missing required variable '{name}'"),
+            ConfigError::InvalidValue { name, value } => {
+                write!(f, "Invalid value '{value}' for variable '{name}'")
+            }
+            ConfigError::Read { path, message } => {
+                write!(f, "Cannot read '{}': {message}", path.display())
+            }
+            ConfigError::Lua {
+                path: Some(path),
+                line: Some(line),
+                message,
+            } => write!(
+                f,
+                "Lua error in '{}' at line {line}: {message}",
+                path.display()
+            ),
+            ConfigError::Lua {
+                path: Some(path),
+                line: None,
+                message,
+            } => write!(f, "Lua error in '{}': {message}", path.display()),
+            ConfigError::Lua {
+                path: None,
+                message,
+                ..
+            } => write!(f, "Lua error: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// A problem that does not prevent the configuration from loading: the affected value is
+/// ignored and its default is used instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigWarning(pub ConfigError);
+
+impl fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConfigError;
+    use std::path::Path;
+
+    #[test]
+    fn lua_error_line() {
+        let path = Path::new("/etc/xdg/rsfm/config.lua");
+        let error = rlua::Error::RuntimeError(
+            "/etc/xdg/rsfm/config.lua:12: attempt to index a nil value\nstack traceback:"
+                .to_string(),
+        );
+
+        assert_eq!(
+            ConfigError::from_lua(Some(path), &error),
+            ConfigError::Lua {
+                path: Some(path.to_path_buf()),
+                line: Some(12),
+                message: "attempt to index a nil value".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn lua_error_without_line() {
+        let error = rlua::Error::RuntimeError("something went wrong".to_string());
+
+        assert_eq!(
+            ConfigError::from_lua(None, &error),
+            ConfigError::Lua {
+                path: None,
+                line: None,
+                message: "something went wrong".to_string(),
+            }
+        );
+    }
+}
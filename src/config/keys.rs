@@ -0,0 +1,471 @@
+use std::collections::HashMap;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    Enter,
+    Parent,
+    Back,
+    Forward,
+    ToggleHidden,
+    ToggleTree,
+    ToggleExpanded,
+    CycleSort,
+    ReverseSort,
+    TogglePreview,
+    Reload,
+    ToggleMark,
+    MarkAll,
+    InvertMarks,
+    ClearMarks,
+    VisualMode,
+    Copy,
+    Cut,
+    Paste,
+    Trash,
+    Delete,
+    ShowTrash,
+    Restore,
+    EmptyTrash,
+    Undo,
+    Redo,
+    Rename,
+    BulkRename,
+    Filter,
+    ClearFilter,
+    Find,
+    Search,
+    SetBookmark,
+    JumpToBookmark,
+    ShowBookmarks,
+    NewTab,
+    OpenInTab,
+    CloseTab,
+    NextTab,
+    PreviousTab,
+    MoveTabLeft,
+    MoveTabRight,
+    /// Starting at 1.
+    SwitchToTab(u8),
+    CycleLayout,
+    SwitchPane,
+    SwapPanes,
+    CopyToPane,
+    MoveToPane,
+    Cancel,
+    Quit,
+}
+
+impl Action {
+    const ALL: [(&'static str, Action); 64] = [
+        ("move_up", Action::MoveUp),
+        ("move_down", Action::MoveDown),
+        ("page_up", Action::PageUp),
+        ("page_down", Action::PageDown),
+        ("top", Action::Top),
+        ("bottom", Action::Bottom),
+        ("enter", Action::Enter),
+        ("parent", Action::Parent),
+        ("back", Action::Back),
+        ("forward", Action::Forward),
+        ("toggle_hidden", Action::ToggleHidden),
+        ("toggle_tree", Action::ToggleTree),
+        ("toggle_expanded", Action::ToggleExpanded),
+        ("cycle_sort", Action::CycleSort),
+        ("reverse_sort", Action::ReverseSort),
+        ("toggle_preview", Action::TogglePreview),
+        ("reload", Action::Reload),
+        ("toggle_mark", Action::ToggleMark),
+        ("mark_all", Action::MarkAll),
+        ("invert_marks", Action::InvertMarks),
+        ("clear_marks", Action::ClearMarks),
+        ("visual_mode", Action::VisualMode),
+        ("copy", Action::Copy),
+        ("cut", Action::Cut),
+        ("paste", Action::Paste),
+        ("trash", Action::Trash),
+        ("delete", Action::Delete),
+        ("show_trash", Action::ShowTrash),
+        ("restore", Action::Restore),
+        ("empty_trash", Action::EmptyTrash),
+        ("undo", Action::Undo),
+        ("redo", Action::Redo),
+        ("rename", Action::Rename),
+        ("bulk_rename", Action::BulkRename),
+        ("filter", Action::Filter),
+        ("clear_filter", Action::ClearFilter),
+        ("find", Action::Find),
+        ("search", Action::Search),
+        ("set_bookmark", Action::SetBookmark),
+        ("jump_to_bookmark", Action::JumpToBookmark),
+        ("show_bookmarks", Action::ShowBookmarks),
+        ("new_tab", Action::NewTab),
+        ("open_in_tab", Action::OpenInTab),
+        ("close_tab", Action::CloseTab),
+        ("next_tab", Action::NextTab),
+        ("previous_tab", Action::PreviousTab),
+        ("move_tab_left", Action::MoveTabLeft),
+        ("move_tab_right", Action::MoveTabRight),
+        ("tab_1", Action::SwitchToTab(1)),
+        ("tab_2", Action::SwitchToTab(2)),
+        ("tab_3", Action::SwitchToTab(3)),
+        ("tab_4", Action::SwitchToTab(4)),
+        ("tab_5", Action::SwitchToTab(5)),
+        ("tab_6", Action::SwitchToTab(6)),
+        ("tab_7", Action::SwitchToTab(7)),
+        ("tab_8", Action::SwitchToTab(8)),
+        ("tab_9", Action::SwitchToTab(9)),
+        ("cycle_layout", Action::CycleLayout),
+        ("switch_pane", Action::SwitchPane),
+        ("swap_panes", Action::SwapPanes),
+        ("copy_to_pane", Action::CopyToPane),
+        ("move_to_pane", Action::MoveToPane),
+        ("cancel", Action::Cancel),
+        ("quit", Action::Quit),
+    ];
+
+    #[allow(clippy::result_unit_err)]
+    pub fn from(name: &str) -> Result<Self, ()> {
+        Action::ALL
+            .iter()
+            .find(|(action_name, _)| *action_name == name)
+            .map(|(_, action)| *action)
+            .ok_or(())
+    }
+
+    pub fn name(&self) -> &'static str {
+        Action::ALL
+            .iter()
+            .find(|(_, action)| action == self)
+            .map(|(name, _)| *name)
+            .unwrap()
+    }
+}
+
+/// A single key press. Shift is folded into the character for printable keys,
+/// so `S` and `<S-s>` are the same key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        match code {
+            KeyCode::BackTab => Key {
+                code: KeyCode::Tab,
+                modifiers: modifiers | KeyModifiers::SHIFT,
+            },
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => Key {
+                code: KeyCode::Char(c.to_ascii_uppercase()),
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            code => Key { code, modifiers },
+        }
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        Key::new(event.code, event.modifiers)
+    }
+}
+
+fn parse_special(name: &str) -> Option<KeyCode> {
+    let code = match name.to_lowercase().as_str() {
+        "enter" | "cr" | "return" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "bs" | "backspace" => KeyCode::Backspace,
+        "del" | "delete" => KeyCode::Delete,
+        "space" => KeyCode::Char(' '),
+        "lt" => KeyCode::Char('<'),
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "insert" => KeyCode::Insert,
+        name => match name.strip_prefix('f').map(str::parse::<u8>) {
+            Some(Ok(number)) if (1..=12).contains(&number) => KeyCode::F(number),
+            _ => {
+                let mut chars = name.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => return None,
+                }
+            }
+        },
+    };
+    Some(code)
+}
+
+/// Parses the contents of `<...>`, such as `C-d`, `S-Tab` or `Enter`.
+fn parse_bracketed(notation: &str) -> Option<Key> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = notation;
+
+    while rest.len() > 2 && rest.as_bytes()[1] == b'-' {
+        modifiers |= match rest.as_bytes()[0].to_ascii_lowercase() {
+            b'c' => KeyModifiers::CONTROL,
+            b's' => KeyModifiers::SHIFT,
+            b'a' | b'm' => KeyModifiers::ALT,
+            _ => return None,
+        };
+        rest = &rest[2..];
+    }
+
+    let code = parse_special(rest)?;
+    // control combinations are reported with lowercase letters
+    let code = match code {
+        KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => {
+            KeyCode::Char(c.to_ascii_lowercase())
+        }
+        code => code,
+    };
+    Some(Key::new(code, modifiers))
+}
+
+/// Parses a key sequence written in vim notation, e.g. `gg`, `<C-d>` or `<Space>x`.
+#[allow(clippy::result_unit_err)]
+pub fn parse_sequence(notation: &str) -> Result<Vec<Key>, ()> {
+    let mut keys = Vec::new();
+    let mut rest = notation;
+
+    while let Some(c) = rest.chars().next() {
+        match rest.find('>') {
+            Some(end) if c == '<' && end > 1 => {
+                keys.push(parse_bracketed(&rest[1..end]).ok_or(())?);
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                keys.push(Key::new(KeyCode::Char(c), KeyModifiers::NONE));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    if keys.is_empty() {
+        Err(())
+    } else {
+        Ok(keys)
+    }
+}
+
+/// What a key sequence is bound to: a built-in action or a command defined in Lua.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Binding {
+    Action(Action),
+    Command(String),
+}
+
+pub enum Lookup {
+    Action(Action),
+    Command(String),
+    /// The keys are the beginning of a longer sequence.
+    Pending,
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    bindings: HashMap<Vec<Key>, Binding>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        const DEFAULT_BINDINGS: [(&str, Action); 76] = [
+            ("k", Action::MoveUp),
+            ("<Up>", Action::MoveUp),
+            ("j", Action::MoveDown),
+            ("<Down>", Action::MoveDown),
+            ("<C-u>", Action::PageUp),
+            ("<PageUp>", Action::PageUp),
+            ("<C-d>", Action::PageDown),
+            ("<PageDown>", Action::PageDown),
+            ("gg", Action::Top),
+            ("<Home>", Action::Top),
+            ("G", Action::Bottom),
+            ("<End>", Action::Bottom),
+            ("l", Action::Enter),
+            ("<Right>", Action::Enter),
+            ("<Enter>", Action::Enter),
+            ("<Left>", Action::Parent),
+            ("<BS>", Action::Parent),
+            ("H", Action::Back),
+            ("<A-Left>", Action::Back),
+            ("<A-Right>", Action::Forward),
+            ("h", Action::ToggleHidden),
+            ("i", Action::ToggleTree),
+            ("za", Action::ToggleExpanded),
+            ("o", Action::ToggleExpanded),
+            ("s", Action::CycleSort),
+            ("S", Action::ReverseSort),
+            ("p", Action::TogglePreview),
+            ("<Space>", Action::ToggleMark),
+            ("<C-a>", Action::MarkAll),
+            ("v", Action::InvertMarks),
+            ("U", Action::ClearMarks),
+            ("V", Action::VisualMode),
+            ("yy", Action::Copy),
+            ("dd", Action::Cut),
+            ("P", Action::Paste),
+            ("D", Action::Trash),
+            ("X", Action::Delete),
+            ("T", Action::ShowTrash),
+            ("R", Action::Restore),
+            ("u", Action::Undo),
+            ("<C-r>", Action::Redo),
+            ("r", Action::Rename),
+            ("E", Action::BulkRename),
+            ("/", Action::Filter),
+            ("<C-l>", Action::ClearFilter),
+            ("<C-f>", Action::Find),
+            ("<C-g>", Action::Search),
+            ("m", Action::SetBookmark),
+            ("'", Action::JumpToBookmark),
+            ("B", Action::ShowBookmarks),
+            ("<C-t>", Action::NewTab),
+            ("t", Action::OpenInTab),
+            ("<C-w>", Action::CloseTab),
+            ("gt", Action::NextTab),
+            ("<Tab>", Action::NextTab),
+            ("gT", Action::PreviousTab),
+            ("<S-Tab>", Action::PreviousTab),
+            ("<", Action::MoveTabLeft),
+            (">", Action::MoveTabRight),
+            ("<A-1>", Action::SwitchToTab(1)),
+            ("<A-2>", Action::SwitchToTab(2)),
+            ("<A-3>", Action::SwitchToTab(3)),
+            ("<A-4>", Action::SwitchToTab(4)),
+            ("<A-5>", Action::SwitchToTab(5)),
+            ("<A-6>", Action::SwitchToTab(6)),
+            ("<A-7>", Action::SwitchToTab(7)),
+            ("<A-8>", Action::SwitchToTab(8)),
+            ("<A-9>", Action::SwitchToTab(9)),
+            ("L", Action::CycleLayout),
+            ("w", Action::SwitchPane),
+            ("W", Action::SwapPanes),
+            ("<F5>", Action::CopyToPane),
+            ("<F6>", Action::MoveToPane),
+            ("<C-c>", Action::Cancel),
+            ("q", Action::Quit),
+            ("<Esc>", Action::Quit),
+        ];
+
+        let mut keymap = Keymap {
+            bindings: HashMap::new(),
+        };
+        for (notation, action) in DEFAULT_BINDINGS {
+            keymap.bind(parse_sequence(notation).unwrap(), action);
+        }
+        keymap
+    }
+}
+
+impl Keymap {
+    pub fn bind(&mut self, keys: Vec<Key>, action: Action) {
+        self.bindings.insert(keys, Binding::Action(action));
+    }
+
+    pub fn bind_command(&mut self, keys: Vec<Key>, command: &str) {
+        self.bindings
+            .insert(keys, Binding::Command(command.to_string()));
+    }
+
+    pub fn unbind(&mut self, keys: &[Key]) {
+        self.bindings.remove(keys);
+    }
+
+    /// Finds the action bound to a sequence. An exact match wins over longer sequences
+    /// starting with the same keys.
+    pub fn lookup(&self, keys: &[Key]) -> Lookup {
+        match self.bindings.get(keys) {
+            Some(Binding::Action(action)) => return Lookup::Action(*action),
+            Some(Binding::Command(command)) => return Lookup::Command(command.clone()),
+            None => {}
+        }
+
+        if self
+            .bindings
+            .keys()
+            .any(|sequence| sequence.len() > keys.len() && sequence.starts_with(keys))
+        {
+            Lookup::Pending
+        } else {
+            Lookup::None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(c: char) -> Key {
+        Key::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn parse_notation() {
+        assert_eq!(parse_sequence("gg"), Ok(vec![key('g'), key('g')]));
+        assert_eq!(
+            parse_sequence("<C-d>"),
+            Ok(vec![Key::new(KeyCode::Char('d'), KeyModifiers::CONTROL)])
+        );
+        assert_eq!(
+            parse_sequence("<C-D>"),
+            Ok(vec![Key::new(KeyCode::Char('d'), KeyModifiers::CONTROL)])
+        );
+        assert_eq!(parse_sequence("<Space>x"), Ok(vec![key(' '), key('x')]));
+        assert_eq!(parse_sequence("<S-s>"), Ok(vec![key('S')]));
+        assert_eq!(
+            parse_sequence("<S-Tab>"),
+            Ok(vec![Key::new(KeyCode::Tab, KeyModifiers::SHIFT)])
+        );
+        assert_eq!(
+            parse_sequence("<F5>"),
+            Ok(vec![Key::new(KeyCode::F(5), KeyModifiers::NONE)])
+        );
+        assert_eq!(parse_sequence("<"), Ok(vec![key('<')]));
+        assert_eq!(parse_sequence("<>"), Ok(vec![key('<'), key('>')]));
+        assert_eq!(parse_sequence("<Bogus>"), Err(()));
+        assert_eq!(parse_sequence(""), Err(()));
+    }
+
+    #[test]
+    fn lookup_sequences() {
+        let keymap = Keymap::default();
+
+        assert!(matches!(
+            keymap.lookup(&[key('j')]),
+            Lookup::Action(Action::MoveDown)
+        ));
+        assert!(matches!(keymap.lookup(&[key('g')]), Lookup::Pending));
+        assert!(matches!(
+            keymap.lookup(&[key('g'), key('g')]),
+            Lookup::Action(Action::Top)
+        ));
+        assert!(matches!(keymap.lookup(&[key('g'), key('x')]), Lookup::None));
+    }
+
+    #[test]
+    fn action_names() {
+        for (name, action) in Action::ALL {
+            assert_eq!(Action::from(name), Ok(action));
+            assert_eq!(action.name(), name);
+        }
+        assert_eq!(Action::from("fly"), Err(()));
+    }
+}
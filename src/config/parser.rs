@@ -1,4 +1,7 @@
-use super::{column, ViewOptions};
+use super::{
+    column, journal, keys, layout, miller, preview, sort, ConfigError, ConfigWarning, ViewOptions,
+};
+use crate::script;
 
 static VARIABLES: phf::Map<&'static str, &'static str> = phf::phf_map! {
     "rsfm.show_hidden" => "boolean",
@@ -8,8 +11,48 @@ static VARIABLES: phf::Map<&'static str, &'static str> = phf::phf_map! {
     "rsfm.entry_format.{}.width" => "integer",
     "rsfm.entry_format.{}.is_fixed_width" => "boolean",
     "rsfm.entry_format.{}.alignment" => "string",
+    "rsfm.entry_format.{}.format" => "string",
+    "rsfm.entry_format.{}.numeric" => "boolean",
+    "rsfm.sort" => "table",
+    "rsfm.sort.by" => "string",
+    "rsfm.sort.reverse" => "boolean",
+    "rsfm.sort.dirs_first" => "boolean",
+    "rsfm.sort.natural" => "boolean",
+    "rsfm.sort.case_sensitive" => "boolean",
+    "rsfm.sort.follow_links" => "boolean",
+    "rsfm.preview" => "table",
+    "rsfm.preview.enabled" => "boolean",
+    "rsfm.preview.width" => "integer",
+    "rsfm.preview.max_lines" => "integer",
+    "rsfm.preview.max_bytes" => "integer",
+    "rsfm.journal" => "table",
+    "rsfm.journal.persist" => "boolean",
+    "rsfm.journal.size" => "integer",
+    "rsfm.layout" => "string",
+    "rsfm.miller" => "table",
+    "rsfm.miller.parent" => "table",
+    "rsfm.miller.parent.width" => "integer",
+    "rsfm.miller.parent.is_fixed_width" => "boolean",
+    "rsfm.miller.current" => "table",
+    "rsfm.miller.current.width" => "integer",
+    "rsfm.miller.current.is_fixed_width" => "boolean",
+    "rsfm.miller.preview" => "table",
+    "rsfm.miller.preview.width" => "integer",
+    "rsfm.miller.preview.is_fixed_width" => "boolean",
+    "rsfm.bookmarks" => "table",
+    "rsfm.bookmarks.*" => "string",
+    "rsfm.keys" => "table",
+    "rsfm.keys.*" => "string",
+    "rsfm.commands" => "table",
+    "rsfm.commands.*" => "function",
 };
 
+// tables with arbitrary keys, their entries are checked against '<table>.*'
+const MAP_VARIABLES: [&str; 3] = ["rsfm.keys", "rsfm.commands", "rsfm.bookmarks"];
+
+/// Action name that removes a default binding.
+const UNBIND_ACTION: &str = "none";
+
 const MAX_SIMILARITY_DISTANCE: usize = 3;
 
 #[derive(Debug)]
@@ -18,52 +61,70 @@ pub struct VarDesc {
     type_name: &'static str,
 }
 
-fn parse_tree(name: &str, value: rlua::Value) -> Vec<VarDesc> {
+fn parse_tree(name: &str, value: rlua::Value, warnings: &mut Vec<ConfigWarning>) -> Vec<VarDesc> {
     let mut actual_vars: Vec<VarDesc> = Vec::new();
-    parse_tree_impl(name, value, &mut actual_vars);
+    parse_tree_impl(name, value, &mut actual_vars, warnings);
     actual_vars
 }
 
-fn parse_tree_impl(name: &str, value: rlua::Value, vector: &mut Vec<VarDesc>) {
+fn parse_tree_impl(
+    name: &str,
+    value: rlua::Value,
+    vector: &mut Vec<VarDesc>,
+    warnings: &mut Vec<ConfigWarning>,
+) {
     if let rlua::Value::Table(table) = value {
-        for pair in table.pairs::<String, rlua::Value>().into_iter() {
+        for pair in table.pairs::<rlua::Value, rlua::Value>() {
             match pair {
-                Ok((key, value)) => {
+                Ok((rlua::Value::String(key), value)) => {
+                    let key = key.to_str().unwrap_or_default();
                     let name = format!("{}.{}", name, key);
                     vector.push(VarDesc {
                         name: name.clone(),
                         type_name: value.type_name(),
                     });
-                    parse_tree_impl(&name, value, vector);
+                    parse_tree_impl(&name, value, vector, warnings);
+                }
+                Ok((rlua::Value::Integer(key), value)) => {
+                    let name = format!("{}.{}", name, key);
+                    vector.push(VarDesc {
+                        name: name.clone(),
+                        type_name: value.type_name(),
+                    });
+                    parse_tree_impl(&name, value, vector, warnings);
+                }
+                Ok((key, _)) => {
+                    warnings.push(ConfigWarning(ConfigError::InvalidValue {
+                        name: format!("{}.<{}>", name, key.type_name()),
+                        value: "key".to_string(),
+                    }));
                 }
                 Err(error) => {
-                    eprintln!("error parsing config.lua: {}", error);
+                    warnings.push(ConfigWarning(ConfigError::from_lua(None, &error)));
                 }
             }
         }
     }
 }
 
-fn find_similar<'a, T>(target: &'a str, src_list: T, max_distance: usize) -> Option<&str>
+fn find_similar<'a, T>(target: &'a str, src_list: T, max_distance: usize) -> Option<&'a str>
 where
     T: Iterator<Item = &'a &'a str>,
 {
-    let mut distance = max_distance + 1;
-    let mut similar = "";
-
-    for expected_name in src_list {
-        let current_distance = levenshtein::levenshtein(&expected_name, target);
-        if current_distance < distance {
-            distance = current_distance;
-            similar = expected_name;
-        }
-    }
-
-    return if distance <= max_distance {
-        Some(similar)
-    } else {
-        None
-    };
+    let parent = |name: &str| name.rsplit_once('.').map(|(parent, _)| parent.to_string());
+    let target_parent = parent(target);
+
+    // ties are broken in favour of siblings of the target, then alphabetically, so that
+    // the suggestion does not depend on the order of the map
+    src_list
+        .map(|expected_name| {
+            let distance = levenshtein::levenshtein(expected_name, target);
+            let is_sibling = parent(expected_name) == target_parent;
+            (distance, !is_sibling, *expected_name)
+        })
+        .filter(|(distance, _, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, _, name)| name)
 }
 
 fn replace_array_index(var: &str) -> String {
@@ -81,169 +142,519 @@ fn replace_array_index(var: &str) -> String {
         .join(SEPARATOR)
 }
 
-pub type CheckResult = Result<Vec<VarDesc>, Vec<String>>;
+/// Replaces array indices and keys of map tables with placeholders used in `VARIABLES`.
+fn normalize_name(var: &str) -> String {
+    for map in MAP_VARIABLES {
+        if let Some(key) = var
+            .strip_prefix(map)
+            .and_then(|rest| rest.strip_prefix('.'))
+        {
+            if !key.is_empty() {
+                return format!("{map}.*");
+            }
+        }
+    }
+    replace_array_index(var)
+}
+
+pub type CheckResult = Result<Vec<VarDesc>, Vec<ConfigWarning>>;
 
 pub fn parse_syntax(root_key: &str, root_value: rlua::Value) -> CheckResult {
-    let actual_vars = parse_tree(root_key, root_value);
+    let mut warnings = Vec::new();
+    let actual_vars = parse_tree(root_key, root_value, &mut warnings);
 
-    let mut errors = Vec::new();
     let mut names_to_skip: Vec<&str> = Vec::new();
 
     actual_vars.iter().for_each(|var| {
         // skip checking underlying table entries if 'Table' is unexpected type
-        if names_to_skip
-            .iter()
-            .find(|&&x| var.name.starts_with(x))
-            .is_some()
-        {
+        if names_to_skip.iter().any(|&x| var.name.starts_with(x)) {
             return;
         }
 
-        let no_arr_index = replace_array_index(&var.name);
+        // the scripting API is part of the table but not of the configuration
+        let is_api_function = var
+            .name
+            .strip_prefix("rsfm.")
+            .is_some_and(|name| script::API_FUNCTIONS.contains(&name));
+        if is_api_function && var.type_name == "function" {
+            return;
+        }
+
+        let no_arr_index = normalize_name(&var.name);
         match VARIABLES.get(&no_arr_index) {
             Some(expected_type_name) => {
                 if var.type_name.ne(*expected_type_name) {
-                    let what = format!(
-                        "Unexpected type '{}' for variable '{}', use '{}'",
-                        var.type_name, var.name, expected_type_name
-                    );
-                    errors.push(what);
+                    warnings.push(ConfigWarning(ConfigError::TypeMismatch {
+                        name: var.name.clone(),
+                        expected: expected_type_name,
+                        actual: var.type_name,
+                    }));
                     names_to_skip.push(&var.name);
                 }
             }
             None => {
-                let what =
-                    match find_similar(&no_arr_index, VARIABLES.keys(), MAX_SIMILARITY_DISTANCE) {
-                        Some(similar) => {
-                            format!(
-                                "Unknown variable '{}'. Did you mean '{}'?",
-                                no_arr_index, similar
-                            )
-                        }
-                        None => {
-                            format!("Unknown variable '{}'", no_arr_index)
-                        }
-                    };
-
-                errors.push(what);
+                let suggestion =
+                    find_similar(&no_arr_index, VARIABLES.keys(), MAX_SIMILARITY_DISTANCE)
+                        .map(str::to_string);
+                warnings.push(ConfigWarning(ConfigError::UnknownVariable {
+                    name: no_arr_index,
+                    suggestion,
+                }));
             }
         }
     });
 
-    if errors.is_empty() {
+    if warnings.is_empty() {
         Ok(actual_vars)
     } else {
-        Err(errors)
+        Err(warnings)
     }
 }
 
-fn parse_show_hidden(table: &rlua::Table, default: bool) -> bool {
-    if let Ok(value) = table.get::<_, bool>("show_hidden") {
-        value
-    } else {
-        default
+/// Reads an optional field of a table, checking its type against `expected`.
+fn get_optional_field<'lua, T: rlua::FromLua<'lua>>(
+    table: &rlua::Table<'lua>,
+    table_name: &str,
+    key: &str,
+    expected: &'static str,
+) -> Result<Option<T>, ConfigError> {
+    let name = format!("{table_name}.{key}");
+    match table.get::<_, rlua::Value>(key) {
+        Ok(rlua::Value::Nil) => Ok(None),
+        Ok(value) if value.type_name() != expected => Err(ConfigError::TypeMismatch {
+            name,
+            expected,
+            actual: value.type_name(),
+        }),
+        Ok(_) => table
+            .get::<_, T>(key)
+            .map(Some)
+            .map_err(|error| ConfigError::from_lua(None, &error)),
+        Err(error) => Err(ConfigError::from_lua(None, &error)),
+    }
+}
+
+/// Reads a required field of a table, reporting it as missing or mistyped.
+fn get_field<'lua, T: rlua::FromLua<'lua>>(
+    table: &rlua::Table<'lua>,
+    table_name: &str,
+    key: &str,
+    expected: &'static str,
+) -> Result<T, ConfigError> {
+    get_optional_field(table, table_name, key, expected)?.ok_or_else(|| ConfigError::MissingField {
+        name: format!("{table_name}.{key}"),
+    })
+}
+
+fn parse_show_hidden(
+    table: &rlua::Table,
+    default: bool,
+    warnings: &mut Vec<ConfigWarning>,
+) -> bool {
+    match get_optional_field(table, "rsfm", "show_hidden", "boolean") {
+        Ok(value) => value.unwrap_or(default),
+        Err(error) => {
+            warnings.push(ConfigWarning(error));
+            default
+        }
     }
 }
 
-fn parse_entry_format(table: &rlua::Table) -> Vec<column::Column> {
+fn parse_column(table: &rlua::Table, name: &str) -> Result<column::Column, ConfigError> {
+    let typename: String = get_field(table, name, "type", "string")?;
+    let mut column_type =
+        column::ColumnType::from(&typename).map_err(|()| ConfigError::InvalidValue {
+            name: format!("{name}.type"),
+            value: typename,
+        })?;
+
+    match &mut column_type {
+        column::ColumnType::Modified { format }
+        | column::ColumnType::Accessed { format }
+        | column::ColumnType::Created { format } => {
+            if let Some(value) = get_optional_field::<String>(table, name, "format", "string")? {
+                use chrono::format::{Item, StrftimeItems};
+                if StrftimeItems::new(&value).any(|item| item == Item::Error) {
+                    return Err(ConfigError::InvalidValue {
+                        name: format!("{name}.format"),
+                        value,
+                    });
+                }
+                *format = value;
+            }
+        }
+        column::ColumnType::Owner { numeric } | column::ColumnType::Group { numeric } => {
+            if let Some(value) = get_optional_field(table, name, "numeric", "boolean")? {
+                *numeric = value;
+            }
+        }
+        _ => {}
+    }
+
+    let width = get_field(table, name, "width", "integer")?;
+    let is_fixed_width = get_field(table, name, "is_fixed_width", "boolean")?;
+
+    let alignment: String = get_field(table, name, "alignment", "string")?;
+    let alignment =
+        column::Alignment::from(&alignment).map_err(|()| ConfigError::InvalidValue {
+            name: format!("{name}.alignment"),
+            value: alignment,
+        })?;
+
+    Ok(column::Column {
+        column_type,
+        width,
+        is_fixed_width,
+        alignment,
+    })
+}
+
+fn parse_entry_format(
+    table: &rlua::Table,
+    default: Vec<column::Column>,
+    warnings: &mut Vec<ConfigWarning>,
+) -> Vec<column::Column> {
     if let Ok(entry_format) = table.get::<_, rlua::Table>("entry_format") {
         entry_format
             .pairs()
             .filter_map(
                 |result: Result<(u16, rlua::Table), rlua::Error>| match result {
-                    Ok(pair) => {
-                        let (index, column_table) = pair;
-
-                        let typename = match column_table.get::<_, String>("type") {
-                            Ok(typename) => typename,
-                            Err(error) => {
-                                eprintln!(
-                                    "Error parsing 'rsfm.entry_format.{index}.type': {error}"
-                                );
-                                return None;
-                            }
-                        };
-
-                        let column_type = match column::ColumnType::from(&typename) {
-                            Ok(column_type) => column_type,
-                            Err(()) => {
-                                eprintln!("Unknown column type name: {typename}");
-                                return None;
-                            }
-                        };
-
-                        let width = match column_table.get::<_, u16>("width") {
-                            Ok(width) => width,
-                            Err(error) => {
-                                eprintln!(
-                                    "Error parsing 'rsfm.entry_format.{index}.width': {error}"
-                                );
-                                return None;
-                            }
-                        };
-
-                        let is_fixed_width = match column_table.contains_key("is_fixed_width") {
-                            Ok(contains) if contains != false => {
-                                column_table.get::<_, bool>("is_fixed_width").unwrap()
-                            },
-                            _ => {
-                                eprintln!(
-                                    "Error parsing 'rsfm.entry_format.{index}.is_fixed_width': value not found"
-                                );
-                                return None;
-                            }
-                        };
-
-                        let alignment = match column_table.get::<_, String>("alignment") {
-                            Ok(typename) => typename,
-                            Err(error) => {
-                                eprintln!(
-                                    "Error parsing 'rsfm.entry_format.{index}.alignment': {error}"
-                                );
-                                return None;
-                            }
-                        };
-
-                        let alignment = match column::Alignment::from(&alignment) {
-                            Ok(alignment) => alignment,
-                            Err(()) => {
-                                eprintln!("Unknown algnment type: {alignment}");
-                                return None;
-                            }
-                        };
-
-                        Some(column::Column { column_type, width, is_fixed_width, alignment})
+                    Ok((index, column_table)) => {
+                        let name = format!("rsfm.entry_format.{index}");
+                        parse_column(&column_table, &name)
+                            .map_err(|error| warnings.push(ConfigWarning(error)))
+                            .ok()
                     }
                     Err(error) => {
-                        eprintln!("Error parsing 'rsfm.entry_format': {error}");
+                        warnings.push(ConfigWarning(ConfigError::from_lua(None, &error)));
                         None
                     }
                 },
             )
             .collect()
     } else {
-        Vec::new()
+        default
     }
 }
 
-fn parse_root(table: rlua::Table) -> ViewOptions {
+fn parse_sort(table: &rlua::Table, warnings: &mut Vec<ConfigWarning>) -> sort::SortOptions {
+    let mut options = sort::SortOptions::default();
+
+    let sort_table = match get_optional_field::<rlua::Table>(table, "rsfm", "sort", "table") {
+        Ok(Some(sort_table)) => sort_table,
+        Ok(None) => return options,
+        Err(error) => {
+            warnings.push(ConfigWarning(error));
+            return options;
+        }
+    };
+
+    const NAME: &str = "rsfm.sort";
+
+    match get_optional_field::<String>(&sort_table, NAME, "by", "string") {
+        Ok(Some(by)) => match sort::SortKey::from(&by) {
+            Ok(by) => options.by = by,
+            Err(()) => warnings.push(ConfigWarning(ConfigError::InvalidValue {
+                name: format!("{NAME}.by"),
+                value: by,
+            })),
+        },
+        Ok(None) => {}
+        Err(error) => warnings.push(ConfigWarning(error)),
+    }
+
+    let mut parse_flag =
+        |key: &str, value: &mut bool| match get_optional_field(&sort_table, NAME, key, "boolean") {
+            Ok(Some(flag)) => *value = flag,
+            Ok(None) => {}
+            Err(error) => warnings.push(ConfigWarning(error)),
+        };
+    parse_flag("reverse", &mut options.reverse);
+    parse_flag("dirs_first", &mut options.dirs_first);
+    parse_flag("natural", &mut options.natural);
+    parse_flag("case_sensitive", &mut options.case_sensitive);
+    parse_flag("follow_links", &mut options.follow_links);
+
+    options
+}
+
+fn parse_preview(
+    table: &rlua::Table,
+    warnings: &mut Vec<ConfigWarning>,
+) -> preview::PreviewOptions {
+    let mut options = preview::PreviewOptions::default();
+
+    let preview_table = match get_optional_field::<rlua::Table>(table, "rsfm", "preview", "table") {
+        Ok(Some(preview_table)) => preview_table,
+        Ok(None) => return options,
+        Err(error) => {
+            warnings.push(ConfigWarning(error));
+            return options;
+        }
+    };
+
+    const NAME: &str = "rsfm.preview";
+
+    match get_optional_field(&preview_table, NAME, "enabled", "boolean") {
+        Ok(Some(enabled)) => options.enabled = enabled,
+        Ok(None) => {}
+        Err(error) => warnings.push(ConfigWarning(error)),
+    }
+
+    match get_optional_field::<u16>(&preview_table, NAME, "width", "integer") {
+        Ok(Some(width)) if (1..100).contains(&width) => options.width = width,
+        Ok(Some(width)) => warnings.push(ConfigWarning(ConfigError::InvalidValue {
+            name: format!("{NAME}.width"),
+            value: width.to_string(),
+        })),
+        Ok(None) => {}
+        Err(error) => warnings.push(ConfigWarning(error)),
+    }
+
+    match get_optional_field(&preview_table, NAME, "max_lines", "integer") {
+        Ok(Some(max_lines)) => options.max_lines = max_lines,
+        Ok(None) => {}
+        Err(error) => warnings.push(ConfigWarning(error)),
+    }
+
+    match get_optional_field(&preview_table, NAME, "max_bytes", "integer") {
+        Ok(Some(max_bytes)) => options.max_bytes = max_bytes,
+        Ok(None) => {}
+        Err(error) => warnings.push(ConfigWarning(error)),
+    }
+
+    options
+}
+
+fn parse_journal(
+    table: &rlua::Table,
+    warnings: &mut Vec<ConfigWarning>,
+) -> journal::JournalOptions {
+    let mut options = journal::JournalOptions::default();
+
+    let journal_table = match get_optional_field::<rlua::Table>(table, "rsfm", "journal", "table") {
+        Ok(Some(journal_table)) => journal_table,
+        Ok(None) => return options,
+        Err(error) => {
+            warnings.push(ConfigWarning(error));
+            return options;
+        }
+    };
+
+    const NAME: &str = "rsfm.journal";
+
+    match get_optional_field(&journal_table, NAME, "persist", "boolean") {
+        Ok(Some(persist)) => options.persist = persist,
+        Ok(None) => {}
+        Err(error) => warnings.push(ConfigWarning(error)),
+    }
+
+    match get_optional_field::<usize>(&journal_table, NAME, "size", "integer") {
+        Ok(Some(size)) if size > 0 => options.size = size,
+        Ok(Some(size)) => warnings.push(ConfigWarning(ConfigError::InvalidValue {
+            name: format!("{NAME}.size"),
+            value: size.to_string(),
+        })),
+        Ok(None) => {}
+        Err(error) => warnings.push(ConfigWarning(error)),
+    }
+
+    options
+}
+
+fn parse_layout(table: &rlua::Table, warnings: &mut Vec<ConfigWarning>) -> layout::Layout {
+    match get_optional_field::<String>(table, "rsfm", "layout", "string") {
+        Ok(Some(name)) => match layout::Layout::from(&name) {
+            Ok(layout) => layout,
+            Err(()) => {
+                warnings.push(ConfigWarning(ConfigError::InvalidValue {
+                    name: String::from("rsfm.layout"),
+                    value: name,
+                }));
+                layout::Layout::default()
+            }
+        },
+        Ok(None) => layout::Layout::default(),
+        Err(error) => {
+            warnings.push(ConfigWarning(error));
+            layout::Layout::default()
+        }
+    }
+}
+
+fn parse_miller(table: &rlua::Table, warnings: &mut Vec<ConfigWarning>) -> miller::MillerOptions {
+    let mut options = miller::MillerOptions::default();
+
+    let miller_table = match get_optional_field::<rlua::Table>(table, "rsfm", "miller", "table") {
+        Ok(Some(miller_table)) => miller_table,
+        Ok(None) => return options,
+        Err(error) => {
+            warnings.push(ConfigWarning(error));
+            return options;
+        }
+    };
+
+    const NAME: &str = "rsfm.miller";
+
+    let mut parse_column = |key: &str, column: &mut miller::ColumnWidth| {
+        let column_table =
+            match get_optional_field::<rlua::Table>(&miller_table, NAME, key, "table") {
+                Ok(Some(column_table)) => column_table,
+                Ok(None) => return,
+                Err(error) => return warnings.push(ConfigWarning(error)),
+            };
+        let name = format!("{NAME}.{key}");
+        match get_optional_field(&column_table, &name, "width", "integer") {
+            Ok(Some(width)) => column.width = width,
+            Ok(None) => {}
+            Err(error) => warnings.push(ConfigWarning(error)),
+        }
+        match get_optional_field(&column_table, &name, "is_fixed_width", "boolean") {
+            Ok(Some(is_fixed_width)) => column.is_fixed_width = is_fixed_width,
+            Ok(None) => {}
+            Err(error) => warnings.push(ConfigWarning(error)),
+        }
+    };
+    parse_column("parent", &mut options.parent);
+    parse_column("current", &mut options.current);
+    parse_column("preview", &mut options.preview);
+
+    options
+}
+
+fn parse_bookmarks(
+    table: &rlua::Table,
+    warnings: &mut Vec<ConfigWarning>,
+) -> std::collections::BTreeMap<char, String> {
+    let mut bookmarks = std::collections::BTreeMap::new();
+
+    let bookmarks_table =
+        match get_optional_field::<rlua::Table>(table, "rsfm", "bookmarks", "table") {
+            Ok(Some(bookmarks_table)) => bookmarks_table,
+            Ok(None) => return bookmarks,
+            Err(error) => {
+                warnings.push(ConfigWarning(error));
+                return bookmarks;
+            }
+        };
+
+    for pair in bookmarks_table.pairs::<String, String>() {
+        let (key, path) = match pair {
+            Ok(pair) => pair,
+            // reported by the syntax check
+            Err(_) => continue,
+        };
+
+        // bookmarks are set and opened by a single key
+        let mut chars = key.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if !path.is_empty() => {
+                bookmarks.insert(c, path);
+            }
+            (Some(_), None) => warnings.push(ConfigWarning(ConfigError::InvalidValue {
+                name: format!("rsfm.bookmarks.{key}"),
+                value: path,
+            })),
+            _ => warnings.push(ConfigWarning(ConfigError::InvalidValue {
+                name: "rsfm.bookmarks".to_string(),
+                value: key,
+            })),
+        }
+    }
+
+    bookmarks
+}
+
+fn parse_keys(table: &rlua::Table, warnings: &mut Vec<ConfigWarning>) -> keys::Keymap {
+    let mut keymap = keys::Keymap::default();
+
+    let keys_table = match get_optional_field::<rlua::Table>(table, "rsfm", "keys", "table") {
+        Ok(Some(keys_table)) => keys_table,
+        Ok(None) => return keymap,
+        Err(error) => {
+            warnings.push(ConfigWarning(error));
+            return keymap;
+        }
+    };
+
+    let commands = get_optional_field::<rlua::Table>(table, "rsfm", "commands", "table")
+        .ok()
+        .flatten();
+    let is_command = |name: &str| match &commands {
+        Some(commands) => matches!(
+            commands.get::<_, rlua::Value>(name),
+            Ok(rlua::Value::Function(_))
+        ),
+        None => false,
+    };
+
+    for pair in keys_table.pairs::<String, String>() {
+        let (notation, action_name) = match pair {
+            Ok(pair) => pair,
+            // reported by the syntax check
+            Err(_) => continue,
+        };
+        let name = format!("rsfm.keys.{notation}");
+
+        let sequence = match keys::parse_sequence(&notation) {
+            Ok(sequence) => sequence,
+            Err(()) => {
+                warnings.push(ConfigWarning(ConfigError::InvalidValue {
+                    name: "rsfm.keys".to_string(),
+                    value: notation,
+                }));
+                continue;
+            }
+        };
+
+        if action_name == UNBIND_ACTION {
+            keymap.unbind(&sequence);
+            continue;
+        }
+
+        match keys::Action::from(&action_name) {
+            Ok(action) => keymap.bind(sequence, action),
+            Err(()) if is_command(&action_name) => keymap.bind_command(sequence, &action_name),
+            Err(()) => warnings.push(ConfigWarning(ConfigError::InvalidValue {
+                name,
+                value: action_name,
+            })),
+        }
+    }
+
+    keymap
+}
+
+fn parse_root(table: rlua::Table, warnings: &mut Vec<ConfigWarning>) -> ViewOptions {
     let mut options = ViewOptions::default();
 
-    options.show_hidden = parse_show_hidden(&table, options.show_hidden);
-    options.entry_format = parse_entry_format(&table);
+    options.show_hidden = parse_show_hidden(&table, options.show_hidden, warnings);
+    let default_entry_format = std::mem::take(&mut options.entry_format);
+    options.entry_format = parse_entry_format(&table, default_entry_format, warnings);
+    options.sort = parse_sort(&table, warnings);
+    options.preview = parse_preview(&table, warnings);
+    options.journal = parse_journal(&table, warnings);
+    options.layout = parse_layout(&table, warnings);
+    options.miller = parse_miller(&table, warnings);
+    options.bookmarks = parse_bookmarks(&table, warnings);
+    options.keys = parse_keys(&table, warnings);
 
     options
 }
 
-pub fn parse_values(root: rlua::Value) -> Result<ViewOptions, rlua::Error> {
+/// Reads the `rsfm` table. Values that cannot be used are skipped and reported alongside.
+pub fn parse_values(root: rlua::Value) -> Result<(ViewOptions, Vec<ConfigWarning>), ConfigError> {
     match root {
         rlua::Value::Table(root) => {
-            let opt = parse_root(root);
-            Ok(opt)
+            let mut warnings = Vec::new();
+            let opt = parse_root(root, &mut warnings);
+            Ok((opt, warnings))
         }
-        _ => Err(rlua::Error::RuntimeError(
-            "Cannot read config.lua root table".to_string(),
-        )),
+        _ => Err(ConfigError::TypeMismatch {
+            name: "rsfm".to_string(),
+            expected: "table",
+            actual: root.type_name(),
+        }),
     }
 }
 
@@ -0,0 +1,251 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+
+// stop walking huge trees at some point
+const MAX_PATHS: usize = 200_000;
+// paths are sent in batches to keep the channel overhead low
+const BATCH_SIZE: usize = 512;
+
+/// Iterates over the entries below `root` the way the finder and the search see them:
+/// without what `.gitignore` files exclude, and without hidden entries unless
+/// `show_hidden` is set.
+pub fn walker(root: &Path, show_hidden: bool) -> ignore::Walk {
+    ignore::WalkBuilder::new(root)
+        .hidden(!show_hidden)
+        // .gitignore files are honoured outside of git repositories too
+        .require_git(false)
+        .build()
+}
+
+/// Walks a directory tree in a background thread, skipping what `.gitignore` files
+/// exclude. The walk stops when this is dropped.
+pub struct Walk {
+    receiver: Receiver<Vec<PathBuf>>,
+    cancelled: Arc<AtomicBool>,
+    done: bool,
+}
+
+impl Walk {
+    pub fn start(root: &Path, show_hidden: bool) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let walker = walker(root, show_hidden);
+        let root = root.to_path_buf();
+        let walk_cancelled = cancelled.clone();
+        std::thread::spawn(move || {
+            let mut batch = Vec::new();
+            let mut count = 0;
+            for entry in walker.filter_map(Result::ok) {
+                if walk_cancelled.load(Ordering::Relaxed) || count >= MAX_PATHS {
+                    break;
+                }
+                match entry.path().strip_prefix(&root) {
+                    Ok(path) if !path.as_os_str().is_empty() => batch.push(path.to_path_buf()),
+                    _ => continue,
+                }
+                count += 1;
+                if batch.len() == BATCH_SIZE && sender.send(std::mem::take(&mut batch)).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send(batch);
+        });
+
+        Walk {
+            receiver,
+            cancelled,
+            done: false,
+        }
+    }
+
+    /// Paths relative to the root found since the last call.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(batch) => paths.extend(batch),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+        paths
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+impl Drop for Walk {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_CONSECUTIVE: i64 = 8;
+// right after a path separator or at the beginning
+const BONUS_BOUNDARY: i64 = 10;
+// after a `_`, `-`, `.` or space, or a lowercase to uppercase change
+const BONUS_WORD: i64 = 8;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP_EXTENSION: i64 = 1;
+
+fn bonus(previous: Option<char>, current: char) -> i64 {
+    match previous {
+        None | Some('/') => BONUS_BOUNDARY,
+        Some('_' | '-' | '.' | ' ') => BONUS_WORD,
+        Some(previous) if previous.is_lowercase() && current.is_uppercase() => BONUS_WORD,
+        _ => 0,
+    }
+}
+
+/// How well a candidate matches, and where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub score: i64,
+    /// Indices of the matched characters, not bytes.
+    pub positions: Vec<usize>,
+}
+
+/// Matches `pattern` as a subsequence of `candidate`, preferring short, consecutive
+/// matches at word boundaries. The case is ignored unless the pattern contains an
+/// uppercase letter.
+pub fn fuzzy_match(pattern: &str, candidate: &str) -> Option<Match> {
+    let ignore_case = !pattern.chars().any(char::is_uppercase);
+    let normalize = |c: char| {
+        if ignore_case {
+            c.to_lowercase().next().unwrap_or(c)
+        } else {
+            c
+        }
+    };
+    let pattern: Vec<char> = pattern.chars().map(normalize).collect();
+    let chars: Vec<char> = candidate.chars().collect();
+    if pattern.is_empty() {
+        return Some(Match {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+
+    // the first place where the whole pattern has been seen
+    let mut matched = 0;
+    let mut end = None;
+    for (index, &c) in chars.iter().enumerate() {
+        if normalize(c) == pattern[matched] {
+            matched += 1;
+            if matched == pattern.len() {
+                end = Some(index);
+                break;
+            }
+        }
+    }
+
+    // going back from there gives the shortest window ending at it
+    let mut positions = Vec::with_capacity(pattern.len());
+    let mut remaining = pattern.iter().rev().peekable();
+    for index in (0..=end?).rev() {
+        match remaining.peek() {
+            Some(&&p) if normalize(chars[index]) == p => {
+                positions.push(index);
+                remaining.next();
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+    positions.reverse();
+
+    let mut score = 0;
+    let mut last: Option<usize> = None;
+    for &index in &positions {
+        let previous = index.checked_sub(1).map(|previous| chars[previous]);
+        score += SCORE_MATCH + bonus(previous, chars[index]);
+        match last {
+            Some(last) if last + 1 == index => score += BONUS_CONSECUTIVE,
+            Some(last) => {
+                let gap = (index - last - 1) as i64;
+                score -= PENALTY_GAP_START + (gap - 1) * PENALTY_GAP_EXTENSION;
+            }
+            None => {}
+        }
+        last = Some(index);
+    }
+
+    Some(Match { score, positions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn best<'a>(pattern: &str, candidates: &[&'a str]) -> &'a str {
+        candidates
+            .iter()
+            .filter_map(|candidate| fuzzy_match(pattern, candidate).map(|m| (m.score, *candidate)))
+            .max_by_key(|(score, candidate)| (*score, std::cmp::Reverse(candidate.len())))
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn matching() {
+        assert_eq!(
+            fuzzy_match("mrs", "src/main.rs").unwrap().positions,
+            vec![4, 9, 10]
+        );
+        assert_eq!(fuzzy_match("xyz", "src/main.rs"), None);
+        assert!(fuzzy_match("Main", "src/main.rs").is_none());
+        assert!(fuzzy_match("main", "src/Main.rs").is_some());
+
+        assert_eq!(
+            best(
+                "main",
+                &["src/domain/xin.rs", "src/main.rs", "maintenance.txt"]
+            ),
+            "src/main.rs"
+        );
+        assert_eq!(best("cfg", &["src/config.rs", "src/cfg.rs"]), "src/cfg.rs");
+        assert_eq!(
+            best("appmod", &["src/app/model.rs", "src/application/mode.rs"]),
+            "src/app/model.rs"
+        );
+    }
+
+    #[test]
+    fn walk() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/nested")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(root.join("src/nested/file"), "").unwrap();
+        std::fs::write(root.join("target/build"), "").unwrap();
+
+        let collect = |show_hidden| {
+            let mut walk = Walk::start(root, show_hidden);
+            let mut paths = Vec::new();
+            while !walk.is_done() {
+                paths.extend(walk.poll());
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            paths.sort();
+            paths
+        };
+
+        let expected: Vec<PathBuf> = ["src", "src/nested", "src/nested/file"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(collect(false), expected);
+        assert!(collect(true).contains(&PathBuf::from(".gitignore")));
+        assert!(!collect(true).contains(&PathBuf::from("target")));
+    }
+}
@@ -0,0 +1,555 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use crate::config::{self, journal::JournalOptions};
+use crate::operation;
+use crate::path_codec::{percent_decode, percent_encode};
+use crate::trash;
+
+const JOURNAL_FILE: &str = "journal";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Renamed or moved.
+    Move,
+    /// Moved into the trash.
+    Trash,
+}
+
+impl ChangeKind {
+    fn name(&self) -> &'static str {
+        match self {
+            ChangeKind::Move => "move",
+            ChangeKind::Trash => "trash",
+        }
+    }
+
+    fn from(name: &str) -> Option<Self> {
+        match name {
+            "move" => Some(ChangeKind::Move),
+            "trash" => Some(ChangeKind::Trash),
+            _ => None,
+        }
+    }
+}
+
+/// An entry that was moved from one path to another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub from: PathBuf,
+    pub to: PathBuf,
+    // device and inode of the entry, to notice when it was replaced by something else
+    id: (u64, u64),
+}
+
+fn file_id(path: &Path) -> Option<(u64, u64)> {
+    fs::symlink_metadata(path)
+        .ok()
+        .map(|metadata| (metadata.dev(), metadata.ino()))
+}
+
+impl Change {
+    /// Describes a change that already happened, the entry is expected at `to`.
+    pub fn new(kind: ChangeKind, from: PathBuf, to: PathBuf) -> io::Result<Self> {
+        let id = file_id(&to).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("'{}' does not exist", to.display()),
+            )
+        })?;
+        Ok(Change { kind, from, to, id })
+    }
+
+    /// Checks that the entry is still at `current` and that nothing took the place of
+    /// `free` in the meantime, then moves it there in `overlay`.
+    fn check(
+        &self,
+        overlay: &mut Overlay,
+        current: &Path,
+        free: Option<&Path>,
+    ) -> Result<(), String> {
+        if overlay.id(current) != Some(self.id) {
+            return Err(format!("'{}' has changed since", current.display()));
+        }
+        match free {
+            Some(free) if overlay.id(free).is_some() => {
+                Err(format!("'{}' already exists", free.display()))
+            }
+            _ => {
+                overlay.rename(current, free, self.id);
+                Ok(())
+            }
+        }
+    }
+
+    fn check_undo(&self, overlay: &mut Overlay) -> Result<(), String> {
+        self.check(overlay, &self.to, Some(&self.from))
+    }
+
+    fn check_redo(&self, overlay: &mut Overlay) -> Result<(), String> {
+        match self.kind {
+            ChangeKind::Move => self.check(overlay, &self.from, Some(&self.to)),
+            // the trash picks a free name by itself
+            ChangeKind::Trash => self.check(overlay, &self.from, None),
+        }
+    }
+
+    fn undo(&mut self) -> io::Result<()> {
+        match self.kind {
+            ChangeKind::Move => operation::move_entry(&self.to, &self.from)?,
+            ChangeKind::Trash => trash::take_out(&self.to, &self.from)?,
+        }
+        // a move to another file system makes a copy, which has a new inode
+        self.id = file_id(&self.from).unwrap_or(self.id);
+        Ok(())
+    }
+
+    fn redo(&mut self) -> io::Result<()> {
+        match self.kind {
+            ChangeKind::Move => operation::move_entry(&self.from, &self.to)?,
+            ChangeKind::Trash => self.to = trash::put(&self.from)?,
+        }
+        self.id = file_id(&self.to).unwrap_or(self.id);
+        Ok(())
+    }
+}
+
+/// The file system as it will be after some changes of an entry, so that all of them
+/// can be checked before touching anything. Needed when the changes depend on each
+/// other, such as two entries swapping their names.
+#[derive(Default)]
+struct Overlay {
+    ids: HashMap<PathBuf, Option<(u64, u64)>>,
+}
+
+impl Overlay {
+    fn id(&self, path: &Path) -> Option<(u64, u64)> {
+        match self.ids.get(path) {
+            Some(id) => *id,
+            None => file_id(path),
+        }
+    }
+
+    fn rename(&mut self, from: &Path, to: Option<&Path>, id: (u64, u64)) {
+        self.ids.insert(from.to_path_buf(), None);
+        if let Some(to) = to {
+            self.ids.insert(to.to_path_buf(), Some(id));
+        }
+    }
+}
+
+/// Changes made by a single operation, undone and redone together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub description: String,
+    pub changes: Vec<Change>,
+}
+
+/// Undo and redo history of renames, moves and trashing.
+pub struct Journal {
+    undo: Vec<Entry>,
+    redo: Vec<Entry>,
+    size: usize,
+    // where the journal is saved between sessions
+    path: Option<PathBuf>,
+}
+
+impl Journal {
+    pub fn new(options: &JournalOptions) -> Self {
+        let path = if options.persist {
+            config::state_dir().map(|dir| dir.join(JOURNAL_FILE))
+        } else {
+            None
+        };
+        Journal::open(options.size, path)
+    }
+
+    /// A journal of at most `size` entries, loaded from and saved to `path` if given.
+    pub fn open(size: usize, path: Option<PathBuf>) -> Self {
+        let mut journal = Journal {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            size,
+            path,
+        };
+        if let Some(contents) = journal
+            .path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+        {
+            journal.parse(&contents);
+        }
+        journal
+    }
+
+    pub fn record(&mut self, entry: Entry) {
+        if entry.changes.is_empty() {
+            return;
+        }
+        self.undo.push(entry);
+        if self.undo.len() > self.size {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        self.save();
+    }
+
+    /// Reverses the last entry and returns its description, `None` if there is nothing
+    /// to undo. Nothing is touched if any of its entries changed in the meantime, the
+    /// entry stays so that undoing again succeeds once that is sorted out. If a change fails after others were undone, the entry keeps
+    /// the remaining changes so that undoing again retries them.
+    pub fn undo(&mut self) -> io::Result<Option<String>> {
+        let mut entry = match self.undo.pop() {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        let mut overlay = Overlay::default();
+        let checked = entry
+            .changes
+            .iter()
+            .rev()
+            .try_for_each(|change| change.check_undo(&mut overlay));
+        if let Err(reason) = checked {
+            let error = io::Error::other(format!("Cannot undo {}: {reason}", entry.description));
+            self.undo.push(entry);
+            return Err(error);
+        }
+
+        // the changes from `remaining` on are undone
+        let mut remaining = entry.changes.len();
+        let mut result = Ok(());
+        while remaining > 0 {
+            if let Err(error) = entry.changes[remaining - 1].undo() {
+                result = Err(error);
+                break;
+            }
+            remaining -= 1;
+        }
+        let undone = entry.changes.split_off(remaining);
+        if !undone.is_empty() {
+            self.redo.push(Entry {
+                description: entry.description.clone(),
+                changes: undone,
+            });
+        }
+        let description = entry.description.clone();
+        if !entry.changes.is_empty() {
+            self.undo.push(entry);
+        }
+        self.save();
+        result.map(|()| Some(description))
+    }
+
+    /// Repeats the last undone entry, see [`Journal::undo`].
+    pub fn redo(&mut self) -> io::Result<Option<String>> {
+        let mut entry = match self.redo.pop() {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        let mut overlay = Overlay::default();
+        let checked = entry
+            .changes
+            .iter()
+            .try_for_each(|change| change.check_redo(&mut overlay));
+        if let Err(reason) = checked {
+            let error = io::Error::other(format!("Cannot redo {}: {reason}", entry.description));
+            self.redo.push(entry);
+            return Err(error);
+        }
+
+        // the changes before `done` are redone
+        let mut done = 0;
+        let mut result = Ok(());
+        for change in &mut entry.changes {
+            if let Err(error) = change.redo() {
+                result = Err(error);
+                break;
+            }
+            done += 1;
+        }
+        let remaining = entry.changes.split_off(done);
+        let description = entry.description.clone();
+        if !entry.changes.is_empty() {
+            self.undo.push(entry);
+        }
+        if !remaining.is_empty() {
+            self.redo.push(Entry {
+                description: description.clone(),
+                changes: remaining,
+            });
+        }
+        self.save();
+        result.map(|()| Some(description))
+    }
+
+    /// One line per entry header and change, with percent-encoded paths:
+    ///
+    /// ```text
+    /// undo <description>
+    /// move <device> <inode> <from> <to>
+    /// redo <description>
+    /// trash <device> <inode> <from> <to>
+    /// ```
+    fn serialize(&self) -> String {
+        let mut contents = String::new();
+        let stacks = [("undo", &self.undo), ("redo", &self.redo)];
+        for (stack_name, entries) in stacks {
+            for entry in entries {
+                contents += &format!(
+                    "{stack_name} {}\n",
+                    percent_encode(Path::new(&entry.description))
+                );
+                for change in &entry.changes {
+                    contents += &format!(
+                        "{} {} {} {} {}\n",
+                        change.kind.name(),
+                        change.id.0,
+                        change.id.1,
+                        percent_encode(&change.from),
+                        percent_encode(&change.to)
+                    );
+                }
+            }
+        }
+        contents
+    }
+
+    fn parse(&mut self, contents: &str) {
+        // the stack the current entry goes to, its changes follow it
+        let mut current: Option<(bool, Entry)> = None;
+
+        for line in contents.lines() {
+            let fields: Vec<_> = line.split(' ').collect();
+            match fields.as_slice() {
+                [stack @ ("undo" | "redo"), description] => {
+                    if let Some((is_undo, entry)) = current.take() {
+                        self.push_loaded(is_undo, entry);
+                    }
+                    let description = percent_decode(description).display().to_string();
+                    current = Some((
+                        *stack == "undo",
+                        Entry {
+                            description,
+                            changes: Vec::new(),
+                        },
+                    ));
+                }
+                [kind, device, inode, from, to] => {
+                    let change = match (ChangeKind::from(kind), device.parse(), inode.parse()) {
+                        (Some(kind), Ok(device), Ok(inode)) => Change {
+                            kind,
+                            from: percent_decode(from),
+                            to: percent_decode(to),
+                            id: (device, inode),
+                        },
+                        // skip what cannot be read rather than refusing the whole file
+                        _ => continue,
+                    };
+                    if let Some((_, entry)) = current.as_mut() {
+                        entry.changes.push(change);
+                    }
+                }
+                _ => continue,
+            }
+        }
+
+        if let Some((is_undo, entry)) = current {
+            self.push_loaded(is_undo, entry);
+        }
+    }
+
+    fn push_loaded(&mut self, is_undo: bool, entry: Entry) {
+        if entry.changes.is_empty() {
+            return;
+        }
+        if is_undo {
+            self.undo.push(entry);
+        } else {
+            self.redo.push(entry);
+        }
+    }
+
+    fn save(&self) {
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            // losing the history is not worth interrupting the user for
+            let _ = fs::write(path, self.serialize());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn move_entry(from: &Path, to: &Path) -> Entry {
+        fs::rename(from, to).unwrap();
+        Entry {
+            description: format!("moving '{}'", from.display()),
+            changes: vec![
+                Change::new(ChangeKind::Move, from.to_path_buf(), to.to_path_buf()).unwrap(),
+            ],
+        }
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("a"), "a").unwrap();
+        let mut journal = Journal::open(10, None);
+
+        journal.record(move_entry(&root.join("a"), &root.join("b")));
+        assert!(journal.undo().unwrap().is_some());
+        assert!(root.join("a").exists() && !root.join("b").exists());
+        assert!(journal.undo().unwrap().is_none());
+
+        assert!(journal.redo().unwrap().is_some());
+        assert!(root.join("b").exists() && !root.join("a").exists());
+
+        // something else took the old place, the entry is kept until it is gone
+        fs::write(root.join("a"), "new").unwrap();
+        let error = journal.undo().unwrap_err().to_string();
+        assert!(error.contains(&format!("'{}'", root.join("a").display())));
+        assert_eq!(fs::read_to_string(root.join("a")).unwrap(), "new");
+        fs::remove_file(root.join("a")).unwrap();
+        assert!(journal.undo().unwrap().is_some());
+        assert!(root.join("a").exists() && !root.join("b").exists());
+
+        // the entry was replaced by another one with the same name
+        fs::rename(root.join("a"), root.join("other")).unwrap();
+        fs::write(root.join("a"), "new").unwrap();
+        let error = journal.redo().unwrap_err().to_string();
+        assert!(error.contains(&format!("'{}'", root.join("a").display())));
+        assert!(!root.join("b").exists());
+        assert_eq!(journal.redo.len(), 1);
+    }
+
+    #[test]
+    fn partial_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for name in ["a", "b", "target_a", "target_b"] {
+            fs::create_dir(root.join(name)).unwrap();
+        }
+        fs::write(root.join("a/file"), "a").unwrap();
+        fs::write(root.join("b/file"), "b").unwrap();
+        let mut entry = move_entry(&root.join("a/file"), &root.join("target_a/file"));
+        let second = move_entry(&root.join("b/file"), &root.join("target_b/file"));
+        entry.changes.extend(second.changes);
+        let mut journal = Journal::open(10, None);
+        journal.record(entry);
+
+        // the second change is undone first, then the first one fails
+        fs::remove_dir(root.join("a")).unwrap();
+        assert!(journal.undo().is_err());
+        assert!(root.join("b/file").exists() && root.join("target_a/file").exists());
+        assert_eq!(journal.undo[0].changes.len(), 1);
+        assert_eq!(journal.redo[0].changes.len(), 1);
+
+        // what is left can still be undone
+        fs::create_dir(root.join("a")).unwrap();
+        assert!(journal.undo().unwrap().is_some());
+        assert!(root.join("a/file").exists());
+        assert!(journal.undo.is_empty());
+
+        // redone in order, the first change of the entry succeeds and the second fails
+        journal.redo.clear();
+        let mut entry = move_entry(&root.join("a/file"), &root.join("target_a/file"));
+        let second = move_entry(&root.join("b/file"), &root.join("target_b/file"));
+        entry.changes.extend(second.changes);
+        journal.record(entry);
+        assert!(journal.undo().unwrap().is_some());
+        fs::remove_dir(root.join("target_b")).unwrap();
+        assert!(journal.redo().is_err());
+        assert!(root.join("target_a/file").exists() && root.join("b/file").exists());
+        assert_eq!(journal.undo[0].changes.len(), 1);
+        assert_eq!(journal.redo[0].changes.len(), 1);
+
+        fs::create_dir(root.join("target_b")).unwrap();
+        assert!(journal.redo().unwrap().is_some());
+        assert!(root.join("target_b/file").exists());
+        assert!(journal.redo.is_empty());
+    }
+
+    #[test]
+    fn undo_across_file_systems() {
+        // another file system is needed, `/dev/shm` usually is one
+        let other = match tempfile::tempdir_in("/dev/shm") {
+            Ok(other) => other,
+            Err(_) => return,
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let device = |path: &Path| fs::metadata(path).unwrap().dev();
+        if device(dir.path()) == device(other.path()) {
+            return;
+        }
+        let from = dir.path().join("file");
+        let to = other.path().join("file");
+        fs::write(&from, "contents").unwrap();
+        operation::move_entry(&from, &to).unwrap();
+
+        let mut journal = Journal::open(10, None);
+        journal.record(Entry {
+            description: "moving 'file'".to_string(),
+            changes: vec![Change::new(ChangeKind::Move, from.clone(), to.clone()).unwrap()],
+        });
+        assert!(journal.undo().unwrap().is_some());
+        assert_eq!(fs::read_to_string(&from).unwrap(), "contents");
+        assert!(!to.exists());
+
+        // the copy has a new inode, which must not count as a change
+        assert!(journal.redo().unwrap().is_some());
+        assert!(to.exists() && !from.exists());
+        assert!(journal.undo().unwrap().is_some());
+        assert!(from.exists());
+    }
+
+    #[test]
+    fn size_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("0"), "").unwrap();
+        let mut journal = Journal::open(2, None);
+
+        for i in 0..3 {
+            journal.record(move_entry(
+                &root.join(i.to_string()),
+                &root.join((i + 1).to_string()),
+            ));
+        }
+        assert!(journal.undo().unwrap().is_some());
+        assert!(journal.undo().unwrap().is_some());
+        assert!(journal.undo().unwrap().is_none());
+        assert!(root.join("1").exists());
+    }
+
+    #[test]
+    fn persistence() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("a file"), "").unwrap();
+        fs::write(root.join("x"), "").unwrap();
+        let path = root.join("state/journal");
+
+        let mut journal = Journal::open(10, Some(path.clone()));
+        journal.record(move_entry(&root.join("a file"), &root.join("b file")));
+        journal.record(move_entry(&root.join("x"), &root.join("y")));
+        journal.undo().unwrap();
+
+        let mut loaded = Journal::open(10, Some(path));
+        assert_eq!(loaded.undo, journal.undo);
+        assert_eq!(loaded.redo, journal.redo);
+
+        assert!(loaded.undo().unwrap().is_some());
+        assert!(root.join("a file").exists());
+    }
+}
@@ -1,38 +1,67 @@
-use tui::style::*;
-use tui::widgets::*;
-
 use std::io;
 
+mod app;
+mod bookmarks;
+pub mod cli;
 mod config;
+mod finder;
+mod journal;
+mod mime;
 mod model;
+mod navigation;
+mod operation;
+mod path_codec;
+mod preview;
+mod rename;
+mod script;
+mod search;
+mod terminal;
+mod trash;
+mod watcher;
 
 // TODO
 pub use config::column;
+pub use config::keys;
+pub use config::layout;
 pub use config::parser;
+pub use config::sort;
 pub use config::ViewOptions;
+pub use config::{ConfigError, ConfigWarning};
+
+// how often background results are picked up while waiting for input
+const TICK: std::time::Duration = std::time::Duration::from_millis(50);
+
+pub fn run(args: cli::Args) -> Result<(), io::Error> {
+    let mut messages: Vec<String> = Vec::new();
 
-fn get_dir_entries(path: &std::path::Path) -> Vec<std::fs::DirEntry> {
-    match std::fs::read_dir(path) {
-        Ok(entries) => entries
-            .filter_map(|result| match result {
-                Ok(entry) => Some(entry),
-                Err(error) => {
-                    eprintln!("{error}");
-                    None
-                }
-            })
-            .collect(),
+    let config_paths = config::config_paths(args.config.as_deref());
+    let script = script::Script::new();
+    let config = match &script {
+        Ok(script) => script.read_config(&config_paths),
+        Err(error) => Err(error.clone()),
+    };
+    let mut options = match config {
+        Ok((options, warnings)) => {
+            messages.extend(warnings.iter().map(ToString::to_string));
+            options
+        }
         Err(error) => {
-            eprintln!("{error}");
-            Vec::new()
+            messages.push(error.to_string());
+            ViewOptions::default()
         }
-    }
-}
+    };
+    args.apply(&mut options);
 
-pub fn run() -> Result<(), io::Error> {
-    let mut options = config::read_config(std::path::Path::new("config.lua"));
+    let start_path = match args.path {
+        Some(path) => path,
+        None => std::env::current_dir()?,
+    };
+    let navigator = navigation::Navigator::new(&start_path, &options)?;
 
-    println!("{:?}", options);
+    let mut app = app::App::new(options, navigator, script.ok());
+    for message in messages {
+        app.show_message(message);
+    }
 
     let stdout = io::stdout();
     let backend = tui::backend::CrosstermBackend::new(stdout);
@@ -43,56 +72,30 @@ pub fn run() -> Result<(), io::Error> {
     )?;
     crossterm::terminal::enable_raw_mode()?;
 
-    let mut selected_index = 0;
-
-    let cwd = std::path::Path::new("/home/midwest");
-    let mut dir_entries = get_dir_entries(&cwd);
+    // drawn only when something changed: an event came or background work made progress
+    let mut needs_draw = true;
+    while !app.should_quit {
+        needs_draw |= app.update();
 
-    loop {
         // -- draw
-        let style_selection = Style::default().fg(Color::Black).bg(Color::LightYellow);
-
-        let mut state = TableState::default();
-        state.select(Some(selected_index));
-
-        let terminal_width = terminal
-            .size()
-            .expect("Cannot retrieve terminal size")
-            .width;
-        terminal.draw(|f| {
-            let size = f.size();
-            let table_data = model::get_table_data(&dir_entries, &options, terminal_width);
-            let list = Table::new(table_data.rows)
-                .block(Block::default().borders(Borders::ALL))
-                .widths(&table_data.widths)
-                .highlight_style(style_selection);
-            f.render_stateful_widget(list, size, &mut state);
-        })?;
+        if needs_draw {
+            needs_draw = false;
+            if app.needs_redraw {
+                app.needs_redraw = false;
+                terminal.clear()?;
+            }
+            terminal.draw(|f| app.draw(f))?;
+        }
 
         // -- input
-        use crossterm::event::KeyCode;
-        match crossterm::event::read()? {
-            crossterm::event::Event::Key(e) => match e.code {
-                KeyCode::Esc | KeyCode::Char('q') => break,
-                KeyCode::Char('j') => selected_index += 1,
-                KeyCode::Char('k') => {
-                    if selected_index > 0 {
-                        selected_index -= 1
-                    }
-                }
-                // KeyCode::Char('l') => {
-                //     if let Some(index) = state.selected() {
-                //         let name = descs[index].name;
-                //     }
-                // }
-                KeyCode::Char('h') => {
-                    options.show_hidden ^= true;
-                    dir_entries = get_dir_entries(&cwd);
-                }
-                _ => {}
-            },
-            _ => {}
+        if !crossterm::event::poll(TICK)? {
+            continue;
+        }
+        // other events, such as resizing the terminal, only need drawing
+        if let crossterm::event::Event::Key(e) = crossterm::event::read()? {
+            app.handle_key(e.into());
         }
+        needs_draw = true;
     }
 
     terminal.show_cursor()?;
@@ -1,5 +1,9 @@
+use clap::Parser;
+
 fn main() {
-    if let Err(e) = rsfm::run() {
+    let args = rsfm::cli::Args::parse();
+
+    if let Err(e) = rsfm::run(args) {
         eprintln!("Application error: {e}");
         std::process::exit(1);
     }
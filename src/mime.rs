@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::SystemTime;
+
+use crate::model::{self, details, Item};
+
+// forgotten all at once past this, so that visiting many directories does not add up
+const MAX_KNOWN: usize = 10_000;
+
+/// A detected type, `None` while the detection is pending.
+struct Known {
+    modified: Option<SystemTime>,
+    mime_type: Option<String>,
+}
+
+fn modified(item: &Item) -> Option<SystemTime> {
+    item.target_metadata
+        .as_ref()
+        .or(item.metadata.as_ref())
+        .and_then(|metadata| metadata.modified().ok())
+}
+
+/// Detects the MIME types of entries in a background thread, as it requires reading
+/// the files, and remembers them until the files are modified.
+pub struct MimeTypes {
+    requests: mpsc::Sender<(PathBuf, Option<SystemTime>)>,
+    results: mpsc::Receiver<(PathBuf, Option<SystemTime>, String)>,
+    known: HashMap<PathBuf, Known>,
+}
+
+impl MimeTypes {
+    pub fn new() -> Self {
+        let (requests, worker_requests) = mpsc::channel::<(PathBuf, Option<SystemTime>)>();
+        let (worker_results, results) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            while let Ok((path, modified)) = worker_requests.recv() {
+                let mime_type = details::mime_type(&path).unwrap_or_default();
+                if worker_results.send((path, modified, mime_type)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        MimeTypes {
+            requests,
+            results,
+            known: HashMap::new(),
+        }
+    }
+
+    /// Asks for the types of `items` that are not known yet or were modified since.
+    pub fn request(&mut self, items: &[Item]) {
+        for item in items {
+            let modified = modified(item);
+            if self
+                .known
+                .get(&item.path)
+                .is_some_and(|known| known.modified == modified)
+            {
+                continue;
+            }
+            if self.known.len() >= MAX_KNOWN {
+                self.known.clear();
+            }
+
+            let mime_type = if item.is_dir() {
+                Some(String::from(model::DIR_MIME_TYPE))
+            } else {
+                let _ = self.requests.send((item.path.clone(), modified));
+                None
+            };
+            let known = Known {
+                modified,
+                mime_type,
+            };
+            self.known.insert(item.path.clone(), known);
+        }
+    }
+
+    /// Picks up types detected since the last call, returns whether there were any.
+    pub fn poll(&mut self) -> bool {
+        let mut detected = false;
+        while let Ok((path, modified, mime_type)) = self.results.try_recv() {
+            // results for files modified in the meantime are outdated
+            if let Some(known) = self
+                .known
+                .get_mut(&path)
+                .filter(|known| known.modified == modified)
+            {
+                known.mime_type = Some(mime_type);
+                detected = true;
+            }
+        }
+        detected
+    }
+
+    /// The type of `item`, `None` until it is detected.
+    pub fn get(&self, item: &Item) -> Option<&str> {
+        self.known
+            .get(&item.path)
+            .filter(|known| known.modified == modified(item))
+            .and_then(|known| known.mime_type.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_in_background() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join("dir")).unwrap();
+        std::fs::write(root.join("text"), "plain text\n").unwrap();
+        let options = crate::config::ViewOptions::default();
+        let items = crate::navigation::read_items(root, &options, &mut Vec::new()).unwrap();
+        let (dir_item, text_item) = (&items[0], &items[1]);
+
+        let mut mime_types = MimeTypes::new();
+        assert_eq!(mime_types.get(text_item), None);
+        mime_types.request(&items);
+        // directories are known without reading anything
+        assert_eq!(mime_types.get(dir_item), Some("inode/directory"));
+
+        let start = std::time::Instant::now();
+        while !mime_types.poll() {
+            assert!(start.elapsed() < std::time::Duration::from_secs(5));
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(mime_types.get(text_item), Some("text/plain"));
+    }
+}
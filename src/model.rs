@@ -5,17 +5,23 @@ use tui::widgets::*;
 use std::collections::HashMap;
 
 use crate::config;
-mod details;
+pub mod details;
+pub mod filter;
+mod sort;
 
 lazy_static::lazy_static! {
     static ref ITEM_STYLES: HashMap<EntryType, Style> = {
         let mut map = HashMap::new();
         map.insert(EntryType::Dir, Style::default().fg(Color::Red).add_modifier(Modifier::BOLD));
         map.insert(EntryType::File, Style::default());
-        map.insert(EntryType::Link, Style::default().fg(Color::Green).add_modifier(Modifier::ITALIC));
+        map.insert(EntryType::LinkToDir, Style::default().fg(Color::Red).add_modifier(Modifier::BOLD | Modifier::ITALIC));
+        map.insert(EntryType::LinkToFile, Style::default().fg(Color::Green).add_modifier(Modifier::ITALIC));
+        map.insert(EntryType::BrokenLink, Style::default().fg(Color::Magenta).add_modifier(Modifier::ITALIC | Modifier::CROSSED_OUT));
         map.insert(EntryType::Unknown, Style::default());
         map
     };
+    // patched onto the style of the entry type
+    static ref MARKED_STYLE: Style = Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD);
 }
 
 lazy_static::lazy_static! {
@@ -23,7 +29,9 @@ lazy_static::lazy_static! {
         let mut map = HashMap::new();
         map.insert(EntryType::File, 2);
         map.insert(EntryType::Dir, 1);
-        map.insert(EntryType::Link, 2);
+        map.insert(EntryType::LinkToDir, 2);
+        map.insert(EntryType::LinkToFile, 2);
+        map.insert(EntryType::BrokenLink, 2);
         map.insert(EntryType::Unknown, 0);
         map
     };
@@ -31,59 +39,135 @@ lazy_static::lazy_static! {
 
 const DIR_SIZE_PLACEHOLDER: &str = "<DIR>";
 const LINK_SIZE_PLACEHOLDER: &str = " --> ";
+const LINK_NAME_SEPARATOR: &str = " -> ";
 const UNKNOWN_SIZE_PLACEHOLDER: &str = "<???>";
+pub const DIR_MIME_TYPE: &str = "inode/directory";
+// shown until the type is detected in the background
+const MIME_TYPE_PLACEHOLDER: &str = "...";
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
-enum EntryType {
+pub enum EntryType {
     Dir,
     File,
-    Link,
+    LinkToDir,
+    LinkToFile,
+    BrokenLink,
     Unknown,
 }
 
 impl EntryType {
-    fn new(metadata: &std::fs::Metadata) -> EntryType {
+    /// Classifies an entry by its own metadata, and by the one of its target if it is a
+    /// symlink.
+    fn new(metadata: &std::fs::Metadata, target: Option<&std::fs::Metadata>) -> EntryType {
         if metadata.is_dir() {
             EntryType::Dir
         } else if metadata.is_file() {
             EntryType::File
         } else if metadata.is_symlink() {
-            EntryType::Link
+            match target {
+                Some(target) if target.is_dir() => EntryType::LinkToDir,
+                Some(_) => EntryType::LinkToFile,
+                None => EntryType::BrokenLink,
+            }
         } else {
             EntryType::Unknown
         }
     }
+
+    /// The type of the entry a link points to, the type itself for other entries.
+    pub fn target_type(&self) -> EntryType {
+        match self {
+            EntryType::LinkToDir => EntryType::Dir,
+            EntryType::LinkToFile => EntryType::File,
+            entry_type => *entry_type,
+        }
+    }
+
+    pub fn is_link(&self) -> bool {
+        matches!(
+            self,
+            EntryType::LinkToDir | EntryType::LinkToFile | EntryType::BrokenLink
+        )
+    }
+}
+
+/// Grouping priority of an entry type, links are grouped with their targets if `follow_links`.
+fn priority(entry_type: EntryType, follow_links: bool) -> u8 {
+    let entry_type = if follow_links {
+        entry_type.target_type()
+    } else {
+        entry_type
+    };
+    *ITEM_PRIORITY.get(&entry_type).unwrap()
 }
 
 #[derive(Debug, Clone)]
-struct Item {
-    name: String,
-    entry_type: EntryType,
-    metadata: Option<std::fs::Metadata>,
+pub struct Item {
+    pub name: String,
+    pub path: std::path::PathBuf,
+    // in a tree, the path of the directory containing a nested entry relative to the listed one
+    pub tree_parent: Option<String>,
+    pub entry_type: EntryType,
+    pub metadata: Option<std::fs::Metadata>,
+    pub link_target: Option<std::path::PathBuf>,
+    // metadata of what a link points to, read once as sorting and drawing need it often
+    pub target_metadata: Option<std::fs::Metadata>,
 }
 
 impl Item {
-    fn from(entry: &std::fs::DirEntry) -> Self {
+    pub fn from(entry: &std::fs::DirEntry) -> Self {
         let name = entry.file_name().to_string_lossy().to_string();
-        let metadata = match entry.metadata() {
-            Ok(metadata) => Some(metadata),
-            Err(error) => {
-                eprintln!("{error}");
-                None
-            }
-        };
+        let path = entry.path();
+        // without metadata the entry is listed with an unknown type
+        let metadata = entry.metadata().ok();
 
+        let target_metadata = match &metadata {
+            Some(metadata) if metadata.is_symlink() => std::fs::metadata(&path).ok(),
+            _ => None,
+        };
         let entry_type = match metadata {
-            Some(ref metadata) => EntryType::new(&metadata),
+            Some(ref metadata) => EntryType::new(metadata, target_metadata.as_ref()),
             None => EntryType::Unknown,
         };
 
+        let link_target = if entry_type.is_link() {
+            std::fs::read_link(&path).ok()
+        } else {
+            None
+        };
+
         Item {
             name,
+            path,
+            tree_parent: None,
             entry_type,
             metadata,
+            link_target,
+            target_metadata,
         }
     }
+
+    /// The path relative to the listed directory, only differing from the name for
+    /// entries nested in a tree.
+    pub fn relative_name(&self) -> String {
+        match &self.tree_parent {
+            Some(parent) => format!("{parent}/{}", self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// The part of the name after the last dot, empty for dotfiles and names without one.
+    pub fn extension(&self) -> &str {
+        match self.name.rfind('.') {
+            Some(0) | None => "",
+            Some(index) => &self.name[index + 1..],
+        }
+    }
+
+    /// Whether the item is a directory or a symlink pointing to one.
+    pub fn is_dir(&self) -> bool {
+        self.entry_type.target_type() == EntryType::Dir
+    }
 }
 
 struct Part {
@@ -91,27 +175,24 @@ struct Part {
     end: usize,
 }
 
-fn split_into_parts(items: &Vec<Item>) -> Vec<Part> {
+fn split_into_parts<F>(items: &[Item], priority: F) -> Vec<Part>
+where
+    F: Fn(&Item) -> u8,
+{
     if items.is_empty() {
         return Vec::new();
     }
 
     let mut result: Vec<Part> = Vec::new();
 
-    let mut current_type = items.first().unwrap().entry_type;
-    let mut current_priority = *ITEM_PRIORITY
-        .get(&items.first().unwrap().entry_type)
-        .unwrap();
+    let mut current_priority = priority(items.first().unwrap());
     let mut begin = 0usize;
 
     for (i, item) in items.iter().enumerate() {
-        if current_type != item.entry_type {
-            current_type = item.entry_type;
-            if current_priority != *ITEM_PRIORITY.get(&item.entry_type).unwrap() {
-                result.push(Part { begin, end: i });
-                begin = i;
-                current_priority = *ITEM_PRIORITY.get(&item.entry_type).unwrap();
-            }
+        if current_priority != priority(item) {
+            result.push(Part { begin, end: i });
+            begin = i;
+            current_priority = priority(item);
         }
     }
     result.push(Part {
@@ -121,74 +202,108 @@ fn split_into_parts(items: &Vec<Item>) -> Vec<Part> {
     result
 }
 
+pub fn item_style(item: &Item) -> Style {
+    *ITEM_STYLES.get(&item.entry_type).unwrap()
+}
+
 pub struct TableData<'a> {
     pub rows: Vec<Row<'a>>,
     pub widths: Vec<Constraint>,
 }
 
-pub fn get_table_data<'a>(
-    entries: &'a Vec<std::fs::DirEntry>,
-    options: &config::ViewOptions,
-    terminal_width: u16,
-) -> TableData<'a> {
-    let items: Vec<_> = entries.iter().map(Item::from).collect();
-
-    let mut items: Vec<_> = items
-        .into_iter()
-        .filter(|item| match item.name.chars().nth(0) {
-            Some(c) if !options.show_hidden && c == '.' => false,
-            _ => true,
-        })
+/// Filters and sorts directory entries the way they are displayed.
+pub fn get_items(entries: &[std::fs::DirEntry], options: &config::ViewOptions) -> Vec<Item> {
+    let mut items: Vec<_> = entries
+        .iter()
+        .map(Item::from)
+        .filter(|item| options.show_hidden || !item.name.starts_with('.'))
         .collect();
 
-    items.sort_by(|a, b| {
-        let get_priority = |item: &Item| -> u8 { *ITEM_PRIORITY.get(&item.entry_type).unwrap() };
-        get_priority(&a)
-            .partial_cmp(&get_priority(&b))
-            .unwrap_or(std::cmp::Ordering::Less)
-    });
-
-    split_into_parts(&items).into_iter().for_each(|part| {
-        items[part.begin..part.end].sort_by(|a, b| {
-            a.name
-                .partial_cmp(&b.name)
+    if options.sort.dirs_first {
+        let get_priority =
+            |item: &Item| -> u8 { priority(item.entry_type, options.sort.follow_links) };
+        items.sort_by(|a, b| {
+            get_priority(a)
+                .partial_cmp(&get_priority(b))
                 .unwrap_or(std::cmp::Ordering::Less)
         });
-    });
 
-    let widths = generate_widths(&options, terminal_width);
+        split_into_parts(&items, get_priority)
+            .into_iter()
+            .for_each(|part| {
+                items[part.begin..part.end].sort_by(|a, b| sort::compare(a, b, &options.sort));
+            });
+    } else {
+        items.sort_by(|a, b| sort::compare(a, b, &options.sort));
+    }
+
+    items
+}
+
+/// Builds the table rows, `is_marked` tells which items (by index) are marked,
+/// `tree_guide` what goes before their names in a tree and `mime_type` their MIME type
+/// if it is known yet.
+pub fn get_table_data<'a, 'g, 'm>(
+    items: &[Item],
+    options: &config::ViewOptions,
+    terminal_width: u16,
+    is_marked: impl Fn(usize) -> bool,
+    tree_guide: impl Fn(usize) -> &'g str,
+    mime_type: impl Fn(&Item) -> Option<&'m str>,
+) -> TableData<'a> {
+    let widths = generate_widths(options, terminal_width);
 
     let rows = items
-        .into_iter()
-        .map(|item| {
-            let style = *ITEM_STYLES.get(&item.entry_type).unwrap();
-            Row::new(generate_columns(item, &options.entry_format, &widths)).style(style)
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let mut style = item_style(item);
+            if is_marked(index) {
+                style = style.patch(*MARKED_STYLE);
+            }
+            let columns = generate_columns(
+                item,
+                tree_guide(index),
+                mime_type(item),
+                &options.entry_format,
+                &widths,
+            );
+            Row::new(columns).style(style)
         })
         .collect();
 
     TableData {
         rows,
-        widths: widths
-            .into_iter()
-            .map(|width| Constraint::Length(width))
-            .collect(),
+        widths: widths.into_iter().map(Constraint::Length).collect(),
     }
 }
 
-fn generate_name(item: &Item) -> String {
-    item.name.clone()
+fn generate_name(item: &Item, tree_guide: &str) -> String {
+    let name = &item.name;
+    match &item.link_target {
+        Some(target) => format!(
+            "{tree_guide}{name}{}{}",
+            LINK_NAME_SEPARATOR,
+            target.display()
+        ),
+        None => format!("{tree_guide}{name}"),
+    }
 }
 
 fn generate_size(item: &Item) -> String {
-    match &item.metadata {
+    let metadata = match item.entry_type {
+        EntryType::LinkToFile => item.target_metadata.as_ref(),
+        EntryType::BrokenLink => return String::from(LINK_SIZE_PLACEHOLDER),
+        _ => item.metadata.as_ref(),
+    };
+
+    match metadata {
         Some(metadata) => {
-            if metadata.is_dir() {
+            if item.is_dir() {
                 String::from(DIR_SIZE_PLACEHOLDER)
             } else if metadata.is_file() {
                 let (size, postfix) = details::human_readable_size(metadata.len());
                 format!("{} {}", size, postfix)
-            } else if metadata.is_symlink() {
-                String::from(LINK_SIZE_PLACEHOLDER)
             } else {
                 String::from(UNKNOWN_SIZE_PLACEHOLDER)
             }
@@ -206,57 +321,136 @@ fn generate_permissions(item: &Item) -> String {
     }
 }
 
+fn generate_time(time: std::io::Result<std::time::SystemTime>, format: &str) -> String {
+    match time {
+        Ok(time) => chrono::DateTime::<chrono::Local>::from(time)
+            .format(format)
+            .to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+fn generate_modified(item: &Item, format: &str) -> String {
+    match &item.metadata {
+        Some(metadata) => generate_time(metadata.modified(), format),
+        None => String::new(),
+    }
+}
+
+fn generate_accessed(item: &Item, format: &str) -> String {
+    match &item.metadata {
+        Some(metadata) => generate_time(metadata.accessed(), format),
+        None => String::new(),
+    }
+}
+
+fn generate_created(item: &Item, format: &str) -> String {
+    match &item.metadata {
+        Some(metadata) => generate_time(metadata.created(), format),
+        None => String::new(),
+    }
+}
+
+fn generate_owner(item: &Item, numeric: bool) -> String {
+    use std::os::unix::fs::MetadataExt;
+
+    match &item.metadata {
+        Some(metadata) if numeric => metadata.uid().to_string(),
+        Some(metadata) => details::user_name(metadata.uid()),
+        None => String::new(),
+    }
+}
+
+fn generate_group(item: &Item, numeric: bool) -> String {
+    use std::os::unix::fs::MetadataExt;
+
+    match &item.metadata {
+        Some(metadata) if numeric => metadata.gid().to_string(),
+        Some(metadata) => details::group_name(metadata.gid()),
+        None => String::new(),
+    }
+}
+
+fn generate_inode(item: &Item) -> String {
+    use std::os::unix::fs::MetadataExt;
+
+    match &item.metadata {
+        Some(metadata) => metadata.ino().to_string(),
+        None => String::new(),
+    }
+}
+
+fn generate_links(item: &Item) -> String {
+    use std::os::unix::fs::MetadataExt;
+
+    match &item.metadata {
+        Some(metadata) => metadata.nlink().to_string(),
+        None => String::new(),
+    }
+}
+
+fn generate_extension(item: &Item) -> String {
+    item.extension().to_string()
+}
+
+fn generate_mime_type(mime_type: Option<&str>) -> String {
+    mime_type.unwrap_or(MIME_TYPE_PLACEHOLDER).to_string()
+}
+
 fn generate_widths(options: &config::ViewOptions, total_width: u16) -> Vec<u16> {
     const BORDER_WIDTH: u16 = 1;
 
     let terminal_width = {
         let column_count = options.entry_format.len() as u16;
-        let occupied_width = 2 * BORDER_WIDTH
-            + if column_count > 1 {
-                column_count - 1
-            } else {
-                0
-            };
-        if total_width < occupied_width {
-            0
-        } else {
-            total_width - occupied_width
-        }
+        let occupied_width = 2 * BORDER_WIDTH + column_count.saturating_sub(1);
+        total_width.saturating_sub(occupied_width)
     };
 
+    let columns: Vec<_> = options
+        .entry_format
+        .iter()
+        .map(|column| (column.width, column.is_fixed_width))
+        .collect();
+    split_width(terminal_width, &columns)
+}
+
+/// Shares `total_width` between columns given as `(width, is_fixed_width)`: fixed columns
+/// get their width and the others split what remains in proportion to theirs.
+pub fn split_width(total_width: u16, columns: &[(u16, bool)]) -> Vec<u16> {
     let mut sum_relative = 0u16;
     let mut sum_fixed = 0u16;
-    for column in &options.entry_format {
-        if column.is_fixed_width {
-            sum_fixed += column.width;
+    for &(width, is_fixed_width) in columns {
+        if is_fixed_width {
+            sum_fixed += width;
         } else {
-            sum_relative += column.width;
+            sum_relative += width;
         };
     }
 
-    let width_unit = if sum_relative == 0 || sum_fixed >= terminal_width {
+    let width_unit = if sum_relative == 0 || sum_fixed >= total_width {
         0.0
     } else {
-        (terminal_width - sum_fixed) as f64 / sum_relative as f64
+        (total_width - sum_fixed) as f64 / sum_relative as f64
     };
 
-    options
-        .entry_format
+    columns
         .iter()
-        .map(|column| {
-            if column.is_fixed_width {
-                column.width
+        .map(|&(width, is_fixed_width)| {
+            if is_fixed_width {
+                width
             } else {
-                (column.width as f64 * width_unit) as u16
+                (width as f64 * width_unit) as u16
             }
         })
         .collect()
 }
 
 fn generate_columns(
-    item: Item,
-    columns: &Vec<config::column::Column>,
-    widths: &Vec<u16>,
+    item: &Item,
+    tree_guide: &str,
+    mime_type: Option<&str>,
+    columns: &[config::column::Column],
+    widths: &[u16],
 ) -> Vec<String> {
     use config::column;
     use config::column::ColumnType;
@@ -281,10 +475,19 @@ fn generate_columns(
                 string.pad(*width as usize, PAD_CHAR, alignment, true)
             };
 
-            match column.column_type {
-                ColumnType::Name => pad(generate_name(&item)),
-                ColumnType::Size => pad(generate_size(&item)),
-                ColumnType::Permissions => pad(generate_permissions(&item)),
+            match &column.column_type {
+                ColumnType::Name => pad(generate_name(item, tree_guide)),
+                ColumnType::Size => pad(generate_size(item)),
+                ColumnType::Permissions => pad(generate_permissions(item)),
+                ColumnType::Modified { format } => pad(generate_modified(item, format)),
+                ColumnType::Accessed { format } => pad(generate_accessed(item, format)),
+                ColumnType::Created { format } => pad(generate_created(item, format)),
+                ColumnType::Owner { numeric } => pad(generate_owner(item, *numeric)),
+                ColumnType::Group { numeric } => pad(generate_group(item, *numeric)),
+                ColumnType::Inode => pad(generate_inode(item)),
+                ColumnType::Links => pad(generate_links(item)),
+                ColumnType::Extension => pad(generate_extension(item)),
+                ColumnType::MimeType => pad(generate_mime_type(mime_type)),
             }
         })
         .collect()
@@ -295,17 +498,19 @@ mod tests {
 
     macro_rules! item_vec {
     ( $( $x:expr ),* ) => {
-        {
-            let mut temp = Vec::new();
+        vec![
             $(
-                temp.push(Item{
+                Item{
                     name: String::new(),
+                    path: std::path::PathBuf::new(),
+                    tree_parent: None,
                     entry_type: $x,
                     metadata: None,
-                });
+                    link_target: None,
+                    target_metadata: None,
+                },
             )*
-            temp
-        }
+        ]
     };
 }
 
@@ -321,10 +526,10 @@ mod tests {
             EntryType::Dir,
             EntryType::File,
             EntryType::File,
-            EntryType::Link
+            EntryType::LinkToFile
         );
 
-        let slices = split_into_parts(&items);
+        let slices = split_into_parts(&items, |item| priority(item.entry_type, false));
         assert_eq!(slices.len(), 3);
 
         assert_eq!(slices[0].begin, 0);
@@ -336,4 +541,106 @@ mod tests {
         assert_eq!(slices[2].begin, 5);
         assert_eq!(slices[2].end, 8);
     }
+
+    #[test]
+    fn split_widths() {
+        use super::*;
+
+        assert_eq!(split_width(100, &[(1, false), (3, false)]), vec![25, 75]);
+        assert_eq!(
+            split_width(100, &[(10, true), (1, false), (2, false)]),
+            vec![10, 30, 60]
+        );
+        assert_eq!(split_width(10, &[(20, true), (1, false)]), vec![20, 0]);
+    }
+
+    #[test]
+    fn symlinks() {
+        use super::*;
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("dir")).unwrap();
+        std::fs::write(root.join("file"), "").unwrap();
+        symlink("dir", root.join("link_to_dir")).unwrap();
+        symlink("file", root.join("link_to_file")).unwrap();
+        symlink("missing", root.join("broken")).unwrap();
+
+        let entries: Vec<_> = std::fs::read_dir(root)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        let mut options = config::ViewOptions::default();
+
+        let items = get_items(&entries, &options);
+        let types: Vec<_> = items
+            .iter()
+            .map(|item| (item.name.as_str(), item.entry_type))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                ("dir", EntryType::Dir),
+                ("broken", EntryType::BrokenLink),
+                ("file", EntryType::File),
+                ("link_to_dir", EntryType::LinkToDir),
+                ("link_to_file", EntryType::LinkToFile),
+            ]
+        );
+        assert_eq!(generate_name(&items[3], ""), "link_to_dir -> dir");
+        assert!(items[3].is_dir());
+        // the size of a link to a file is the one of the file
+        assert_eq!(generate_size(&items[4]), "0 B");
+
+        options.sort.follow_links = true;
+        let names: Vec<_> = get_items(&entries, &options)
+            .into_iter()
+            .map(|item| item.name)
+            .collect();
+        assert_eq!(
+            names,
+            vec!["dir", "link_to_dir", "broken", "file", "link_to_file"]
+        );
+    }
+
+    #[test]
+    fn render_without_config() {
+        use super::*;
+        use tui::widgets::Table;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("file"), "content").unwrap();
+
+        // no configuration file at all
+        let (options, warnings) = config::read_config(&rlua::Lua::new(), &[]).unwrap();
+        assert!(warnings.is_empty());
+
+        let entries: Vec<_> = std::fs::read_dir(root)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        let items = get_items(&entries, &options);
+        let table_data = get_table_data(&items, &options, 40, |_| false, |_| "", |_| None);
+
+        let backend = tui::backend::TestBackend::new(40, 1);
+        let mut terminal = tui::Terminal::new(backend).unwrap();
+        terminal
+            .draw(|f| {
+                let table = Table::new(table_data.rows).widths(&table_data.widths);
+                f.render_widget(table, f.size());
+            })
+            .unwrap();
+        let line: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol.as_str())
+            .collect();
+        assert!(line.starts_with("file "), "{line}");
+        assert!(line.contains("7 B"), "{line}");
+        assert!(line.contains("-rw"), "{line}");
+    }
 }
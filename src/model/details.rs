@@ -1,15 +1,13 @@
 lazy_static::lazy_static! {
-    static ref SIZE_POSTFIX: Vec<String> = {
-        let mut vec = Vec::new();
-        vec.push("B".to_string());
-        vec.push("K".to_string());
-        vec.push("M".to_string());
-        vec.push("G".to_string());
-        vec.push("T".to_string());
-        vec.push("P".to_string());
-        vec.push("X".to_string());
-        vec
-    };
+    static ref SIZE_POSTFIX: Vec<String> = vec![
+        "B".to_string(),
+        "K".to_string(),
+        "M".to_string(),
+        "G".to_string(),
+        "T".to_string(),
+        "P".to_string(),
+        "X".to_string(),
+    ];
 }
 
 pub fn human_readable_size(size_in_bytes: u64) -> (f64, String) {
@@ -20,7 +18,7 @@ pub fn human_readable_size(size_in_bytes: u64) -> (f64, String) {
     let mut i = 0;
 
     if size < UPPER_LIMIT_LEFT {
-        return (size, SIZE_POSTFIX.get(0).unwrap().into());
+        return (size, SIZE_POSTFIX.first().unwrap().into());
     } else {
         while size >= UPPER_LIMIT_LEFT {
             i += 1;
@@ -34,6 +32,97 @@ pub fn human_readable_size(size_in_bytes: u64) -> (f64, String) {
     )
 }
 
+lazy_static::lazy_static! {
+    static ref USER_NAMES: std::sync::Mutex<std::collections::HashMap<u32, String>> =
+        Default::default();
+    static ref GROUP_NAMES: std::sync::Mutex<std::collections::HashMap<u32, String>> =
+        Default::default();
+}
+
+/// Resolves a user name, falling back to the numeric id for unknown users.
+pub fn user_name(uid: u32) -> String {
+    USER_NAMES
+        .lock()
+        .unwrap()
+        .entry(uid)
+        .or_insert_with(|| match users::get_user_by_uid(uid) {
+            Some(user) => user.name().to_string_lossy().to_string(),
+            None => uid.to_string(),
+        })
+        .clone()
+}
+
+/// Resolves a group name, falling back to the numeric id for unknown groups.
+pub fn group_name(gid: u32) -> String {
+    GROUP_NAMES
+        .lock()
+        .unwrap()
+        .entry(gid)
+        .or_insert_with(|| match users::get_group_by_gid(gid) {
+            Some(group) => group.name().to_string_lossy().to_string(),
+            None => gid.to_string(),
+        })
+        .clone()
+}
+
+/// Whether a buffer looks like text: valid UTF-8 without NUL bytes. A multibyte character
+/// cut at the end of the buffer is tolerated, as buffers are usually file prefixes.
+pub fn is_text(buffer: &[u8]) -> bool {
+    if buffer.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(buffer) {
+        Ok(_) => true,
+        Err(error) => error.error_len().is_none(),
+    }
+}
+
+/// The MIME type of an entry that is not a regular file, which must not be read as one:
+/// reading a FIFO blocks, and a device may never end.
+pub fn special_mime_type(file_type: std::fs::FileType) -> Option<&'static str> {
+    use std::os::unix::fs::FileTypeExt;
+
+    if file_type.is_dir() {
+        Some("inode/directory")
+    } else if file_type.is_fifo() {
+        Some("inode/fifo")
+    } else if file_type.is_socket() {
+        Some("inode/socket")
+    } else if file_type.is_char_device() {
+        Some("inode/chardevice")
+    } else if file_type.is_block_device() {
+        Some("inode/blockdevice")
+    } else {
+        None
+    }
+}
+
+/// Detects the MIME type of a file from its content, or from its type if it is not a
+/// regular file.
+pub fn mime_type(path: &std::path::Path) -> Option<String> {
+    use std::io::Read;
+
+    const PREFIX_LENGTH: u64 = 8192;
+
+    // links are followed, like when the file is opened
+    let metadata = std::fs::metadata(path).ok()?;
+    if let Some(mime_type) = special_mime_type(metadata.file_type()) {
+        return Some(mime_type.to_string());
+    }
+
+    let mut buffer = Vec::new();
+    std::fs::File::open(path)
+        .and_then(|file| file.take(PREFIX_LENGTH).read_to_end(&mut buffer))
+        .ok()?;
+
+    match infer::get(&buffer) {
+        Some(kind) => Some(kind.mime_type().to_string()),
+        None if buffer.is_empty() => Some(String::from("inode/x-empty")),
+        None if is_text(&buffer) => Some(String::from("text/plain")),
+        None => Some(String::from("application/octet-stream")),
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -48,9 +137,37 @@ mod tests {
             super::human_readable_size(1047527424)
         );
         assert_eq!((0.98, "G".into()), super::human_readable_size(1048576000));
+        assert_eq!((16.0, "X".into()), super::human_readable_size(u64::MAX));
+    }
+
+    #[test]
+    fn is_text() {
+        assert!(super::is_text(b"plain text\n"));
+        assert!(super::is_text("юникод".as_bytes()));
+        assert!(super::is_text(&"юникод".as_bytes()[..3]));
+        assert!(!super::is_text(b"\x7fELF\x02\x01\x01\x00"));
+        assert!(!super::is_text(&[0xff, 0xfe, 0x41, 0x42]));
+    }
+
+    #[test]
+    fn mime_type_of_special_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let fifo = dir.path().join("fifo");
+        let status = std::process::Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        // would block if the FIFO was opened
+        assert_eq!(super::mime_type(&fifo).as_deref(), Some("inode/fifo"));
+        assert_eq!(
+            super::mime_type(std::path::Path::new("/dev/null")).as_deref(),
+            Some("inode/chardevice")
+        );
         assert_eq!(
-            (16.0, "X".into()),
-            super::human_readable_size(u64::max_value())
+            super::mime_type(dir.path()).as_deref(),
+            Some("inode/directory")
         );
     }
 }
@@ -0,0 +1,136 @@
+/// How the filter pattern is matched against entry names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterMode {
+    /// The name contains the pattern.
+    Substring,
+    /// The whole name matches a glob such as `*.rs`.
+    Glob,
+    /// The name contains a match of a regular expression.
+    Regex,
+}
+
+impl FilterMode {
+    pub fn next(self) -> Self {
+        match self {
+            FilterMode::Substring => FilterMode::Glob,
+            FilterMode::Glob => FilterMode::Regex,
+            FilterMode::Regex => FilterMode::Substring,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FilterMode::Substring => "substring",
+            FilterMode::Glob => "glob",
+            FilterMode::Regex => "regex",
+        }
+    }
+}
+
+/// The last line of a regex error, the others point at the position in the pattern.
+pub fn regex_error(error: &regex::Error) -> String {
+    let message = error.to_string();
+    message
+        .lines()
+        .last()
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Substring(String),
+    Glob(globset::GlobMatcher),
+    Regex(regex::Regex),
+}
+
+/// Narrows the listing down to the entries whose names match a pattern. The case is
+/// ignored unless the pattern contains an uppercase letter.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    pub pattern: String,
+    pub mode: FilterMode,
+    ignore_case: bool,
+    matcher: Matcher,
+}
+
+impl Filter {
+    pub fn new(pattern: &str, mode: FilterMode) -> Result<Self, String> {
+        let ignore_case = !pattern.chars().any(char::is_uppercase);
+        let matcher = match mode {
+            FilterMode::Substring if ignore_case => Matcher::Substring(pattern.to_lowercase()),
+            FilterMode::Substring => Matcher::Substring(pattern.to_string()),
+            FilterMode::Glob => globset::GlobBuilder::new(pattern)
+                .case_insensitive(ignore_case)
+                // names have no separators, but `*` should not stop at one anyway
+                .literal_separator(false)
+                .build()
+                .map(|glob| Matcher::Glob(glob.compile_matcher()))
+                .map_err(|error| error.kind().to_string())?,
+            FilterMode::Regex => regex::RegexBuilder::new(pattern)
+                .case_insensitive(ignore_case)
+                .build()
+                .map(Matcher::Regex)
+                .map_err(|error| regex_error(&error))?,
+        };
+
+        Ok(Filter {
+            pattern: pattern.to_string(),
+            mode,
+            ignore_case,
+            matcher,
+        })
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match &self.matcher {
+            Matcher::Substring(pattern) if self.ignore_case => {
+                name.to_lowercase().contains(pattern.as_str())
+            }
+            Matcher::Substring(pattern) => name.contains(pattern.as_str()),
+            Matcher::Glob(glob) => glob.is_match(name),
+            Matcher::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matching(pattern: &str, mode: FilterMode) -> Vec<&'static str> {
+        let filter = Filter::new(pattern, mode).unwrap();
+        ["main.rs", "Makefile", "README.md", "lib.RS"]
+            .into_iter()
+            .filter(|name| filter.matches(name))
+            .collect()
+    }
+
+    #[test]
+    fn modes_and_case() {
+        assert_eq!(
+            matching("ma", FilterMode::Substring),
+            vec!["main.rs", "Makefile"]
+        );
+        assert_eq!(matching("Ma", FilterMode::Substring), vec!["Makefile"]);
+        assert_eq!(
+            matching("*.rs", FilterMode::Glob),
+            vec!["main.rs", "lib.RS"]
+        );
+        assert_eq!(matching("*.RS", FilterMode::Glob), vec!["lib.RS"]);
+        assert_eq!(matching("rs", FilterMode::Glob), Vec::<&str>::new());
+        assert_eq!(
+            matching(r"^[a-z]+\.", FilterMode::Regex),
+            vec!["main.rs", "README.md", "lib.RS"]
+        );
+        assert_eq!(
+            matching(r"^[A-Z]", FilterMode::Regex),
+            vec!["Makefile", "README.md"]
+        );
+
+        assert!(Filter::new("[", FilterMode::Glob).is_err());
+        assert!(Filter::new("(", FilterMode::Regex).is_err());
+        assert!(Filter::new("(", FilterMode::Substring).is_ok());
+    }
+}
@@ -0,0 +1,166 @@
+use std::cmp::Ordering;
+use std::os::unix::fs::MetadataExt;
+
+use super::{EntryType, Item};
+use crate::config::sort::{SortKey, SortOptions};
+
+/// Compares strings treating runs of digits as numbers, so that `file9` goes before `file10`.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                        digits.push(c);
+                    }
+                    digits
+                };
+                let x = take_number(&mut a);
+                let y = take_number(&mut b);
+
+                // compare by value without overflowing: shorter number without leading zeros
+                // is smaller, equal length numbers compare as strings
+                let x_trimmed = x.trim_start_matches('0');
+                let y_trimmed = y.trim_start_matches('0');
+                let ordering = x_trimmed
+                    .len()
+                    .cmp(&y_trimmed.len())
+                    .then_with(|| x_trimmed.cmp(y_trimmed))
+                    .then_with(|| x.len().cmp(&y.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn compare_names(a: &str, b: &str, options: &SortOptions) -> Ordering {
+    let compare = |a: &str, b: &str| {
+        if options.natural {
+            natural_cmp(a, b)
+        } else {
+            a.cmp(b)
+        }
+    };
+
+    if options.case_sensitive {
+        compare(a, b)
+    } else {
+        compare(&a.to_lowercase(), &b.to_lowercase()).then_with(|| compare(a, b))
+    }
+}
+
+fn type_order(entry_type: EntryType, follow_links: bool) -> u8 {
+    let entry_type = if follow_links {
+        entry_type.target_type()
+    } else {
+        entry_type
+    };
+
+    match entry_type {
+        EntryType::Dir => 0,
+        EntryType::File => 1,
+        EntryType::LinkToDir => 2,
+        EntryType::LinkToFile => 3,
+        EntryType::BrokenLink => 4,
+        EntryType::Unknown => 5,
+    }
+}
+
+fn size(item: &Item, follow_links: bool) -> u64 {
+    if follow_links && item.entry_type == EntryType::LinkToFile {
+        return item
+            .target_metadata
+            .as_ref()
+            .map_or(0, |metadata| metadata.len());
+    }
+
+    match &item.metadata {
+        Some(metadata) if metadata.is_file() => metadata.len(),
+        _ => 0,
+    }
+}
+
+fn modified(item: &Item) -> Option<std::time::SystemTime> {
+    item.metadata.as_ref()?.modified().ok()
+}
+
+fn changed(item: &Item) -> Option<(i64, i64)> {
+    let metadata = item.metadata.as_ref()?;
+    Some((metadata.ctime(), metadata.ctime_nsec()))
+}
+
+/// Orders two items by the configured key, falling back to their names.
+pub fn compare(a: &Item, b: &Item, options: &SortOptions) -> Ordering {
+    let by_name = || compare_names(&a.name, &b.name, options);
+
+    let ordering = match options.by {
+        SortKey::Name => by_name(),
+        SortKey::Size => size(a, options.follow_links)
+            .cmp(&size(b, options.follow_links))
+            .then_with(by_name),
+        SortKey::Modified => modified(a).cmp(&modified(b)).then_with(by_name),
+        SortKey::Changed => changed(a).cmp(&changed(b)).then_with(by_name),
+        SortKey::Extension => {
+            compare_names(a.extension(), b.extension(), options).then_with(by_name)
+        }
+        SortKey::Type => type_order(a.entry_type, options.follow_links)
+            .cmp(&type_order(b.entry_type, options.follow_links))
+            .then_with(|| compare_names(a.extension(), b.extension(), options))
+            .then_with(by_name),
+    };
+
+    if options.reverse {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_order() {
+        let mut names = vec!["file10", "file9", "file1", "file01", "a", "file", "file9b"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            vec!["a", "file", "file1", "file01", "file9", "file9b", "file10"]
+        );
+
+        assert_eq!(
+            natural_cmp("99999999999999999999999", "100000000000000000000000"),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn case_insensitive_order() {
+        let options = SortOptions {
+            case_sensitive: false,
+            ..SortOptions::default()
+        };
+        assert_eq!(compare_names("B", "a", &options), Ordering::Greater);
+        assert_eq!(compare_names("a", "A", &options), Ordering::Greater);
+
+        let options = SortOptions::default();
+        assert_eq!(compare_names("B", "a", &options), Ordering::Less);
+    }
+}
@@ -0,0 +1,820 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::ViewOptions;
+use crate::model::filter::Filter;
+use crate::model::{self, Item};
+
+// directories remembered to go back to
+const MAX_HISTORY: usize = 100;
+
+/// `path` made absolute with `.` and `..` resolved by name, the way a shell does, so
+/// that the parent of a link to a directory is the directory containing the link.
+fn logical_path(path: &Path) -> std::io::Result<PathBuf> {
+    let mut logical = PathBuf::new();
+    for component in std::env::current_dir()?.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                logical.pop();
+            }
+            component => logical.push(component),
+        }
+    }
+    // fails like reading it would when it does not exist
+    std::fs::metadata(&logical)?;
+    Ok(logical)
+}
+
+/// Whether `a` and `b` lead to the same directory, through links or not.
+fn same_dir(a: &Path, b: &Path) -> bool {
+    a == b || matches!((a.canonicalize(), b.canonicalize()), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Reads the entries of `path` as they are listed. Entries that cannot be read are left
+/// out, with their errors added to `errors`.
+pub fn read_items(
+    path: &Path,
+    options: &ViewOptions,
+    errors: &mut Vec<String>,
+) -> std::io::Result<Vec<Item>> {
+    let entries: Vec<_> = std::fs::read_dir(path)?
+        .filter_map(|result| match result {
+            Ok(entry) => Some(entry),
+            Err(error) => {
+                errors.push(format!("{}: {error}", path.display()));
+                None
+            }
+        })
+        .collect();
+
+    Ok(model::get_items(&entries, options))
+}
+
+/// Entries of a directory and of the expanded directories below it, each one followed by
+/// its children. The expanded directories that were read are added to `dirs`.
+fn read_tree(
+    path: &Path,
+    options: &ViewOptions,
+    expanded: &HashSet<String>,
+    dirs: &mut Vec<PathBuf>,
+    errors: &mut Vec<String>,
+) -> std::io::Result<Vec<Item>> {
+    let level = read_items(path, options, errors)?;
+    let mut reader = TreeReader {
+        options,
+        expanded,
+        items: Vec::new(),
+        dirs,
+        errors,
+    };
+    reader.add_level(level, None);
+    Ok(reader.items)
+}
+
+/// What is drawn before the names of the nested entries of a tree, by relative name.
+/// Worked out from the entries shown, so that it still fits when some are filtered out.
+fn tree_guides(items: &[Item]) -> HashMap<String, String> {
+    // an entry is the last one of its directory when no entry after it has the same parent
+    let mut parents = HashSet::new();
+    let mut is_last = vec![false; items.len()];
+    for (index, item) in items.iter().enumerate().rev() {
+        is_last[index] = parents.insert(item.tree_parent.as_deref());
+    }
+
+    let mut guides = HashMap::new();
+    // the indentation of the children of every directory
+    let mut indents: HashMap<String, String> = HashMap::new();
+    for (item, is_last) in items.iter().zip(is_last) {
+        let parent = match &item.tree_parent {
+            Some(parent) => parent,
+            // top-level entries are not indented
+            None => continue,
+        };
+        let indent = indents.get(parent).map_or("", String::as_str);
+        let branch = if is_last { "└─ " } else { "├─ " };
+        let child_indent = format!("{indent}{}", if is_last { "   " } else { "│  " });
+        guides.insert(item.relative_name(), format!("{indent}{branch}"));
+        indents.insert(item.relative_name(), child_indent);
+    }
+    guides
+}
+
+/// What is built while reading a tree, level by level.
+struct TreeReader<'a> {
+    options: &'a ViewOptions,
+    expanded: &'a HashSet<String>,
+    items: Vec<Item>,
+    dirs: &'a mut Vec<PathBuf>,
+    errors: &'a mut Vec<String>,
+}
+
+impl TreeReader<'_> {
+    fn add_level(&mut self, level: Vec<Item>, parent: Option<&str>) {
+        for mut item in level {
+            item.tree_parent = parent.map(str::to_string);
+            let name = item.relative_name();
+            let children = match self.expanded.contains(&name) && item.is_dir() {
+                // children that cannot be read are left out
+                true => read_items(&item.path, self.options, self.errors).ok(),
+                false => None,
+            };
+            if children.is_some() {
+                self.dirs.push(item.path.clone());
+            }
+            self.items.push(item);
+            if let Some(children) = children {
+                self.add_level(children, Some(&name));
+            }
+        }
+    }
+}
+
+/// Current directory, its listing and the cursor position within it.
+pub struct Navigator {
+    cwd: PathBuf,
+    // the listing before filtering
+    all_items: Vec<Item>,
+    items: Vec<Item>,
+    filter: Option<Filter>,
+    selected_index: usize,
+    // name of the last selected entry of every visited directory
+    last_selected: HashMap<PathBuf, String>,
+    // relative names of marked entries of the current directory
+    marked: HashSet<String>,
+    // where the visual range selection started, the range ends at the cursor
+    visual_anchor: Option<usize>,
+    // relative names of the expanded directories when the listing is shown as a tree
+    tree: Option<HashSet<String>>,
+    // what is drawn before the names of nested entries of the tree, by relative name
+    tree_guides: HashMap<String, String>,
+    // the expanded directories of the tree that could be read
+    tree_dirs: Vec<PathBuf>,
+    // entries that could not be read, until they are shown
+    errors: Vec<String>,
+    // directories visited before the current one, and the ones left by going back
+    back: Vec<PathBuf>,
+    forward: Vec<PathBuf>,
+}
+
+impl Navigator {
+    /// Opens `path`, or the directory containing it with it selected if `path` is not a directory.
+    pub fn new(path: &Path, options: &ViewOptions) -> std::io::Result<Self> {
+        let path = logical_path(path)?;
+        let (cwd, selected_name) = match (path.is_dir(), path.parent(), path.file_name()) {
+            (false, Some(parent), Some(name)) => (
+                parent.to_path_buf(),
+                Some(name.to_string_lossy().to_string()),
+            ),
+            _ => (path, None),
+        };
+        let mut errors = Vec::new();
+        let items = read_items(&cwd, options, &mut errors)?;
+
+        let mut navigator = Navigator {
+            cwd,
+            all_items: Vec::new(),
+            items: Vec::new(),
+            filter: None,
+            selected_index: 0,
+            last_selected: HashMap::new(),
+            marked: HashSet::new(),
+            visual_anchor: None,
+            tree: None,
+            tree_guides: HashMap::new(),
+            tree_dirs: Vec::new(),
+            errors,
+            back: Vec::new(),
+            forward: Vec::new(),
+        };
+        navigator.set_items(items);
+        if let Some(name) = selected_name {
+            navigator.select_name(&name);
+        }
+        Ok(navigator)
+    }
+
+    pub fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+
+    /// Errors of the entries left out of the listing since the last call.
+    pub fn take_errors(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.errors)
+    }
+
+    fn set_items(&mut self, items: Vec<Item>) {
+        self.all_items = items;
+        self.items = match &self.filter {
+            Some(filter) => {
+                // in a tree, the directories containing a match are kept to show where it is
+                let mut shown = HashSet::new();
+                for item in self
+                    .all_items
+                    .iter()
+                    .filter(|item| filter.matches(&item.name))
+                {
+                    shown.insert(item.relative_name());
+                    let mut parent = item.tree_parent.as_deref();
+                    while let Some(name) = parent {
+                        if !shown.insert(name.to_string()) {
+                            break;
+                        }
+                        parent = name.rsplit_once('/').map(|(parent, _)| parent);
+                    }
+                }
+                self.all_items
+                    .iter()
+                    .filter(|item| shown.contains(&item.relative_name()))
+                    .cloned()
+                    .collect()
+            }
+            None => self.all_items.clone(),
+        };
+        self.tree_guides = tree_guides(&self.items);
+    }
+
+    pub fn filter(&self) -> Option<&Filter> {
+        self.filter.as_ref()
+    }
+
+    /// Shows only the entries matching `filter`, until it is replaced or removed.
+    /// The cursor stays on the same entry when it is still shown.
+    pub fn set_filter(&mut self, filter: Option<Filter>) {
+        let selected_name = self.selected_item().map(Item::relative_name);
+        self.filter = filter;
+        let items = std::mem::take(&mut self.all_items);
+        self.set_items(items);
+
+        match selected_name {
+            Some(name) if self.select_name(&name) => {}
+            _ => self.select_index(self.selected_index),
+        }
+        // the range would cover other entries now
+        self.visual_anchor = None;
+    }
+
+    pub fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    pub fn selected_item(&self) -> Option<&Item> {
+        self.items.get(self.selected_index)
+    }
+
+    pub fn select_index(&mut self, index: usize) {
+        self.selected_index = index.min(self.items.len().saturating_sub(1));
+    }
+
+    pub fn select_next(&mut self) {
+        self.select_index(self.selected_index + 1);
+    }
+
+    /// Moves the cursor to the entry called `name`, a path relative to the listed
+    /// directory in a tree, returns `false` if there is none.
+    pub fn select_name(&mut self, name: &str) -> bool {
+        match self
+            .items
+            .iter()
+            .position(|item| item.relative_name() == name)
+        {
+            Some(index) => {
+                self.selected_index = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn visual_range(&self) -> Option<std::ops::RangeInclusive<usize>> {
+        self.visual_anchor
+            .map(|anchor| anchor.min(self.selected_index)..=anchor.max(self.selected_index))
+    }
+
+    /// Whether the entry is marked or within the visual range.
+    pub fn is_marked(&self, index: usize) -> bool {
+        self.visual_range()
+            .is_some_and(|range| range.contains(&index))
+            || self
+                .items
+                .get(index)
+                .is_some_and(|item| self.marked.contains(&item.relative_name()))
+    }
+
+    /// Marked entries in display order, the visual range included.
+    pub fn marked_items(&self) -> Vec<&Item> {
+        (0..self.items.len())
+            .filter(|&index| self.is_marked(index))
+            .map(|index| &self.items[index])
+            .collect()
+    }
+
+    /// Toggles the mark of the selected entry and moves to the next one.
+    pub fn toggle_mark(&mut self) {
+        if let Some(item) = self.selected_item() {
+            let name = item.relative_name();
+            if !self.marked.remove(&name) {
+                self.marked.insert(name);
+            }
+            self.select_next();
+        }
+    }
+
+    pub fn mark_all(&mut self) {
+        self.marked = self.items.iter().map(Item::relative_name).collect();
+    }
+
+    pub fn invert_marks(&mut self) {
+        self.marked = self
+            .items
+            .iter()
+            .map(Item::relative_name)
+            .filter(|name| !self.marked.contains(name))
+            .collect();
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+        self.visual_anchor = None;
+    }
+
+    pub fn in_visual_mode(&self) -> bool {
+        self.visual_anchor.is_some()
+    }
+
+    /// Starts a visual range at the cursor, or ends it marking the entries within it.
+    pub fn toggle_visual_mode(&mut self) {
+        match self.visual_range() {
+            Some(range) => {
+                let names: Vec<_> = self.items[range].iter().map(Item::relative_name).collect();
+                self.marked.extend(names);
+                self.visual_anchor = None;
+            }
+            None if !self.items.is_empty() => self.visual_anchor = Some(self.selected_index),
+            None => {}
+        }
+    }
+
+    /// Leaves visual mode without marking the range.
+    pub fn cancel_visual_mode(&mut self) {
+        self.visual_anchor = None;
+    }
+
+    /// Reads `path`, as a tree with the directories in `expanded` if given.
+    fn read(
+        &mut self,
+        path: &Path,
+        options: &ViewOptions,
+        expanded: Option<&HashSet<String>>,
+    ) -> std::io::Result<Vec<Item>> {
+        match expanded {
+            Some(expanded) => {
+                let mut dirs = Vec::new();
+                let items = read_tree(path, options, expanded, &mut dirs, &mut self.errors)?;
+                self.tree_dirs = dirs;
+                Ok(items)
+            }
+            None => {
+                self.tree_dirs.clear();
+                read_items(path, options, &mut self.errors)
+            }
+        }
+    }
+
+    /// The directories whose entries are listed: the current one and, in a tree, the
+    /// expanded ones below it.
+    pub fn listed_dirs(&self) -> Vec<PathBuf> {
+        std::iter::once(&self.cwd)
+            .chain(&self.tree_dirs)
+            .cloned()
+            .collect()
+    }
+
+    pub fn in_tree_mode(&self) -> bool {
+        self.tree.is_some()
+    }
+
+    /// What goes before the name of an entry: the indentation guide of nested entries
+    /// in a tree, nothing otherwise.
+    pub fn tree_guide(&self, index: usize) -> &str {
+        self.items
+            .get(index)
+            .and_then(|item| self.tree_guides.get(&item.relative_name()))
+            .map_or("", String::as_str)
+    }
+
+    /// Shows the listing as a tree, or flat again with every directory collapsed.
+    pub fn toggle_tree(&mut self, options: &ViewOptions) -> std::io::Result<()> {
+        // the cursor goes to the top-level entry containing a nested one
+        let top_name = self.selected_item().map(|item| match &item.tree_parent {
+            Some(parent) => parent.split('/').next().unwrap_or_default().to_string(),
+            None => item.name.clone(),
+        });
+        self.tree = match self.tree {
+            Some(_) => None,
+            None => Some(HashSet::new()),
+        };
+        self.reload(options)?;
+        if let Some(name) = top_name {
+            self.select_name(&name);
+        }
+        Ok(())
+    }
+
+    /// Expands the selected directory of the tree, or collapses it if it is expanded.
+    /// On any other entry, collapses the directory containing it.
+    pub fn toggle_expanded(&mut self, options: &ViewOptions) -> std::io::Result<()> {
+        let (expanded, item) = match (self.tree.as_mut(), self.items.get(self.selected_index)) {
+            (Some(expanded), Some(item)) => (expanded, item),
+            _ => return Ok(()),
+        };
+        let name = item.relative_name();
+        if expanded.remove(&name) {
+            return self.reload(options);
+        }
+        if item.is_dir() {
+            expanded.insert(name);
+            return self.reload(options);
+        }
+        if let Some(parent) = item.tree_parent.clone() {
+            expanded.remove(&parent);
+            self.reload(options)?;
+            self.select_name(&parent);
+        }
+        Ok(())
+    }
+
+    /// Re-reads the current directory keeping the cursor on the same entry when possible.
+    pub fn reload(&mut self, options: &ViewOptions) -> std::io::Result<()> {
+        let selected_name = self.selected_item().map(Item::relative_name);
+        let cwd = self.cwd.clone();
+        let expanded = self.tree.take();
+        let result = self.read(&cwd, options, expanded.as_ref());
+        self.tree = expanded;
+        self.set_items(result?);
+
+        match selected_name {
+            Some(name) if self.select_name(&name) => {}
+            _ => self.select_index(self.selected_index),
+        }
+
+        // marks of entries that are gone or hidden now are dropped, filtered out ones
+        // are kept but not acted upon
+        let names: HashSet<_> = self.all_items.iter().map(Item::relative_name).collect();
+        self.marked.retain(|name| names.contains(name));
+        if let Some(anchor) = self.visual_anchor {
+            self.visual_anchor = Some(anchor.min(self.items.len().saturating_sub(1)));
+        }
+        Ok(())
+    }
+
+    /// Enters the selected entry if it is a directory or a link to one.
+    pub fn enter(&mut self, options: &ViewOptions) -> std::io::Result<()> {
+        match self.selected_item() {
+            Some(item) if item.is_dir() => {
+                let path = item.path.clone();
+                self.change_dir(&path, options)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Goes to the parent directory selecting the one we came from.
+    pub fn parent(&mut self, options: &ViewOptions) -> std::io::Result<()> {
+        let (parent, name) = match (self.cwd.parent(), self.cwd.file_name()) {
+            (Some(parent), Some(name)) => {
+                (parent.to_path_buf(), name.to_string_lossy().to_string())
+            }
+            _ => return Ok(()),
+        };
+
+        self.change_dir(&parent, options)?;
+        self.select_name(&name);
+        Ok(())
+    }
+
+    /// Goes to `path`, remembering the current directory to come back to.
+    pub fn change_dir(&mut self, path: &Path, options: &ViewOptions) -> std::io::Result<()> {
+        let previous = self.cwd.clone();
+        self.visit(path, options)?;
+        if !same_dir(&self.cwd, &previous) {
+            self.back.push(previous);
+            if self.back.len() > MAX_HISTORY {
+                self.back.remove(0);
+            }
+            self.forward.clear();
+        }
+        Ok(())
+    }
+
+    /// Returns to the directory visited before the current one, if any.
+    pub fn back(&mut self, options: &ViewOptions) -> std::io::Result<()> {
+        if let Some(path) = self.back.pop() {
+            let current = self.cwd.clone();
+            // a directory that is gone is dropped from the history
+            self.visit(&path, options)?;
+            self.forward.push(current);
+        }
+        Ok(())
+    }
+
+    /// Goes to the directory left by going back, if any.
+    pub fn forward(&mut self, options: &ViewOptions) -> std::io::Result<()> {
+        if let Some(path) = self.forward.pop() {
+            let current = self.cwd.clone();
+            self.visit(&path, options)?;
+            self.back.push(current);
+        }
+        Ok(())
+    }
+
+    fn visit(&mut self, path: &Path, options: &ViewOptions) -> std::io::Result<()> {
+        let path = logical_path(path)?;
+        // directories of a tree start collapsed
+        let expanded = self.tree.as_ref().map(|_| HashSet::new());
+        let items = self.read(&path, options, expanded.as_ref())?;
+        self.tree = expanded;
+
+        if let Some(item) = self.selected_item() {
+            self.last_selected
+                .insert(self.cwd.clone(), item.relative_name());
+        }
+
+        self.cwd = path;
+        self.set_items(items);
+        self.selected_index = 0;
+        self.clear_marks();
+
+        if let Some(name) = self.last_selected.get(&self.cwd).cloned() {
+            self.select_name(&name);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The directory is removed when the returned guard is dropped.
+    fn make_tree() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("a")).unwrap();
+        std::fs::create_dir_all(root.join("b/nested")).unwrap();
+        std::fs::write(root.join("b/file"), "").unwrap();
+        std::fs::write(root.join("c"), "").unwrap();
+        (dir, root)
+    }
+
+    #[test]
+    fn enter_and_parent() {
+        let (_dir, root) = make_tree();
+        let options = ViewOptions::default();
+        let mut navigator = Navigator::new(&root, &options).unwrap();
+
+        assert!(navigator.select_name("b"));
+        navigator.enter(&options).unwrap();
+        assert_eq!(navigator.cwd(), root.join("b"));
+        assert_eq!(navigator.selected_item().unwrap().name, "nested");
+
+        navigator.parent(&options).unwrap();
+        assert_eq!(navigator.cwd(), root);
+        assert_eq!(navigator.selected_item().unwrap().name, "b");
+    }
+
+    #[test]
+    fn parent_of_link() {
+        let (_dir, root) = make_tree();
+        std::os::unix::fs::symlink(root.join("b/nested"), root.join("a/link")).unwrap();
+        let options = ViewOptions::default();
+        let mut navigator = Navigator::new(&root.join("a"), &options).unwrap();
+
+        assert!(navigator.select_name("link"));
+        navigator.enter(&options).unwrap();
+        assert_eq!(navigator.cwd(), root.join("a/link"));
+
+        // back to where the link is, not to the parent of its target
+        navigator.parent(&options).unwrap();
+        assert_eq!(navigator.cwd(), root.join("a"));
+        assert_eq!(navigator.selected_item().unwrap().name, "link");
+
+        let navigator = Navigator::new(&root.join("a/link/.."), &options).unwrap();
+        assert_eq!(navigator.cwd(), root.join("a"));
+    }
+
+    #[test]
+    fn restore_cursor() {
+        let (_dir, root) = make_tree();
+        let options = ViewOptions::default();
+        let mut navigator = Navigator::new(&root, &options).unwrap();
+
+        navigator.select_name("b");
+        navigator.enter(&options).unwrap();
+        navigator.select_name("file");
+        navigator.parent(&options).unwrap();
+        navigator.enter(&options).unwrap();
+        assert_eq!(navigator.selected_item().unwrap().name, "file");
+    }
+
+    #[test]
+    fn open_file() {
+        let (_dir, root) = make_tree();
+        let options = ViewOptions::default();
+        let navigator = Navigator::new(&root.join("b/file"), &options).unwrap();
+
+        assert_eq!(navigator.cwd(), root.join("b"));
+        assert_eq!(navigator.selected_item().unwrap().name, "file");
+    }
+
+    #[test]
+    fn enter_file_is_noop() {
+        let (_dir, root) = make_tree();
+        let options = ViewOptions::default();
+        let mut navigator = Navigator::new(&root, &options).unwrap();
+
+        navigator.select_name("c");
+        navigator.enter(&options).unwrap();
+        assert_eq!(navigator.cwd(), root);
+    }
+
+    #[test]
+    fn marks() {
+        let (_dir, root) = make_tree();
+        let options = ViewOptions::default();
+        let mut navigator = Navigator::new(&root, &options).unwrap();
+        let marked_names = |navigator: &Navigator| -> Vec<String> {
+            navigator
+                .marked_items()
+                .iter()
+                .map(|item| item.name.clone())
+                .collect()
+        };
+
+        navigator.toggle_mark();
+        assert_eq!(marked_names(&navigator), vec!["a"]);
+        assert_eq!(navigator.selected_item().unwrap().name, "b");
+
+        navigator.invert_marks();
+        assert_eq!(marked_names(&navigator), vec!["b", "c"]);
+
+        navigator.clear_marks();
+        navigator.toggle_visual_mode();
+        navigator.select_next();
+        assert!(navigator.is_marked(1) && navigator.is_marked(2));
+        navigator.cancel_visual_mode();
+        assert!(marked_names(&navigator).is_empty());
+
+        navigator.select_index(0);
+        navigator.toggle_visual_mode();
+        navigator.select_next();
+        navigator.toggle_visual_mode();
+        assert_eq!(marked_names(&navigator), vec!["a", "b"]);
+
+        std::fs::remove_file(root.join("c")).unwrap();
+        navigator.mark_all();
+        navigator.reload(&options).unwrap();
+        assert_eq!(marked_names(&navigator), vec!["a", "b"]);
+
+        navigator.enter(&options).unwrap();
+        assert!(marked_names(&navigator).is_empty());
+    }
+
+    #[test]
+    fn history() {
+        let (_dir, root) = make_tree();
+        let options = ViewOptions::default();
+        let mut navigator = Navigator::new(&root, &options).unwrap();
+
+        navigator.select_name("b");
+        navigator.enter(&options).unwrap();
+        navigator.select_name("nested");
+        navigator.enter(&options).unwrap();
+        navigator.back(&options).unwrap();
+        assert_eq!(navigator.cwd(), root.join("b"));
+        assert_eq!(navigator.selected_item().unwrap().name, "nested");
+        navigator.back(&options).unwrap();
+        assert_eq!(navigator.cwd(), root);
+        // nothing further back
+        navigator.back(&options).unwrap();
+        assert_eq!(navigator.cwd(), root);
+
+        navigator.forward(&options).unwrap();
+        assert_eq!(navigator.cwd(), root.join("b"));
+        // going somewhere else drops what was ahead
+        navigator.change_dir(&root.join("a"), &options).unwrap();
+        navigator.forward(&options).unwrap();
+        assert_eq!(navigator.cwd(), root.join("a"));
+        navigator.back(&options).unwrap();
+        assert_eq!(navigator.cwd(), root.join("b"));
+    }
+
+    #[test]
+    fn tree() {
+        let (_dir, root) = make_tree();
+        std::fs::write(root.join("b/nested/.hidden"), "").unwrap();
+        std::fs::write(root.join("b/nested/deep"), "").unwrap();
+        let mut options = ViewOptions::default();
+        let mut navigator = Navigator::new(&root, &options).unwrap();
+        let rows = |navigator: &Navigator| -> Vec<String> {
+            (0..navigator.items().len())
+                .map(|index| {
+                    let name = &navigator.items()[index].name;
+                    format!("{}{name}", navigator.tree_guide(index))
+                })
+                .collect()
+        };
+
+        // nothing to expand outside of the tree mode
+        navigator.select_name("b");
+        navigator.toggle_expanded(&options).unwrap();
+        assert_eq!(rows(&navigator), vec!["a", "b", "c"]);
+
+        navigator.toggle_tree(&options).unwrap();
+        navigator.toggle_expanded(&options).unwrap();
+        assert!(navigator.select_name("b/nested"));
+        navigator.toggle_expanded(&options).unwrap();
+        assert_eq!(
+            rows(&navigator),
+            vec!["a", "b", "├─ nested", "│  └─ deep", "└─ file", "c"]
+        );
+
+        // hidden entries are shown at every depth
+        options.show_hidden = true;
+        navigator.reload(&options).unwrap();
+        assert_eq!(navigator.items()[3].name, ".hidden");
+        assert_eq!(navigator.items()[3].relative_name(), "b/nested/.hidden");
+        assert_eq!(navigator.tree_guide(4), "│  └─ ");
+        assert_eq!(
+            navigator.listed_dirs(),
+            vec![root.clone(), root.join("b"), root.join("b/nested")]
+        );
+
+        // collapses the directory containing the entry
+        assert!(navigator.select_name("b/nested/deep"));
+        navigator.toggle_expanded(&options).unwrap();
+        assert_eq!(
+            navigator.selected_item().unwrap().relative_name(),
+            "b/nested"
+        );
+        assert_eq!(navigator.items().len(), 5);
+
+        navigator.enter(&options).unwrap();
+        assert_eq!(navigator.cwd(), root.join("b/nested"));
+        navigator.parent(&options).unwrap();
+        assert_eq!(rows(&navigator), vec!["nested", "file"]);
+
+        navigator.toggle_tree(&options).unwrap();
+        assert!(!navigator.in_tree_mode());
+    }
+
+    #[test]
+    fn filter() {
+        use crate::model::filter::FilterMode;
+
+        let (_dir, root) = make_tree();
+        let options = ViewOptions::default();
+        let mut navigator = Navigator::new(&root, &options).unwrap();
+        let names = |navigator: &Navigator| -> Vec<String> {
+            navigator
+                .items()
+                .iter()
+                .map(|item| item.name.clone())
+                .collect()
+        };
+
+        navigator.select_name("c");
+        navigator.set_filter(Some(Filter::new("[bc]", FilterMode::Glob).unwrap()));
+        assert_eq!(names(&navigator), vec!["b", "c"]);
+        assert_eq!(navigator.selected_item().unwrap().name, "c");
+
+        // kept when the directory is read again
+        std::fs::write(root.join("bb"), "").unwrap();
+        navigator.reload(&options).unwrap();
+        assert_eq!(names(&navigator), vec!["b", "c"]);
+
+        navigator.set_filter(None);
+        assert_eq!(names(&navigator), vec!["a", "b", "bb", "c"]);
+        assert_eq!(navigator.selected_item().unwrap().name, "c");
+
+        // in a tree, the directories containing a match are kept
+        std::fs::write(root.join("b/nested/deep"), "").unwrap();
+        navigator.toggle_tree(&options).unwrap();
+        navigator.select_name("b");
+        navigator.toggle_expanded(&options).unwrap();
+        navigator.select_name("b/nested");
+        navigator.toggle_expanded(&options).unwrap();
+        navigator.set_filter(Some(Filter::new("deep", FilterMode::Glob).unwrap()));
+        let rows: Vec<_> = (0..navigator.items().len())
+            .map(|index| {
+                let name = &navigator.items()[index].name;
+                format!("{}{name}", navigator.tree_guide(index))
+            })
+            .collect();
+        assert_eq!(rows, vec!["b", "└─ nested", "   └─ deep"]);
+    }
+}
@@ -0,0 +1,759 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+use crate::trash;
+
+const BUFFER_SIZE: usize = 64 * 1024;
+// progress is sent at most this often, so that the event loop is not flooded
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Copy,
+    Move,
+    Trash,
+    Delete,
+}
+
+impl Kind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::Copy => "Copying",
+            Kind::Move => "Moving",
+            Kind::Trash => "Trashing",
+            Kind::Delete => "Deleting",
+        }
+    }
+
+    pub fn past_name(&self) -> &'static str {
+        match self {
+            Kind::Copy => "Copied",
+            Kind::Move => "Moved",
+            Kind::Trash => "Trashed",
+            Kind::Delete => "Deleted",
+        }
+    }
+}
+
+/// What to do when the destination of a copy or move already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Overwrite,
+    Skip,
+    /// Keep both, the new entry gets a free name such as `file (1).txt`.
+    Rename,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    /// The file being processed.
+    pub current: Option<PathBuf>,
+    pub file_bytes_done: u64,
+    pub file_bytes_total: u64,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    /// Counts entries of all kinds, directories and links included.
+    pub files_done: u64,
+    pub files_total: u64,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// Average speed in bytes per second.
+    pub fn throughput(&self) -> u64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            (self.bytes_done as f64 / seconds) as u64
+        } else {
+            0
+        }
+    }
+
+    /// Overall progress between 0 and 1.
+    pub fn ratio(&self) -> f64 {
+        if self.bytes_total > 0 {
+            (self.bytes_done as f64 / self.bytes_total as f64).min(1.0)
+        } else if self.files_total > 0 {
+            (self.files_done as f64 / self.files_total as f64).min(1.0)
+        } else {
+            0.0
+        }
+    }
+
+    pub fn file_ratio(&self) -> f64 {
+        if self.file_bytes_total > 0 {
+            (self.file_bytes_done as f64 / self.file_bytes_total as f64).min(1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The outcome of a finished operation.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Sources that were processed, with their new location unless they were deleted.
+    /// The location of trashed entries is inside the trash.
+    pub completed: Vec<(PathBuf, Option<PathBuf>)>,
+    pub skipped: usize,
+    pub errors: Vec<String>,
+    pub cancelled: bool,
+}
+
+enum Event {
+    Progress(Progress),
+    Conflict(PathBuf),
+    Finished(Report),
+}
+
+// `None` cancels the operation
+type Reply = Option<(Resolution, bool)>;
+
+fn cancelled_error() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "cancelled")
+}
+
+/// A free name next to `path`: `name (1).ext`, `name (2).ext` and so on.
+fn unique_path(path: &Path) -> PathBuf {
+    let is_dir = path.is_dir();
+    let stem = match path.file_stem() {
+        Some(stem) if !is_dir => stem.to_string_lossy().to_string(),
+        _ => path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    };
+    let extension = match path.extension() {
+        Some(extension) if !is_dir => format!(".{}", extension.to_string_lossy()),
+        _ => String::new(),
+    };
+
+    (1..)
+        .map(|n| path.with_file_name(format!("{stem} ({n}){extension}")))
+        .find(|candidate| fs::symlink_metadata(candidate).is_err())
+        .unwrap()
+}
+
+/// Counts entries and bytes below `path`, links are not followed.
+fn measure(path: &Path) -> (u64, u64) {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return (1, 0),
+    };
+
+    if metadata.is_dir() {
+        let children = fs::read_dir(path)
+            .map(|entries| entries.filter_map(Result::ok).collect::<Vec<_>>())
+            .unwrap_or_default();
+        children
+            .iter()
+            .map(|entry| measure(&entry.path()))
+            .fold((1, 0), |(files, bytes), (child_files, child_bytes)| {
+                (files + child_files, bytes + child_bytes)
+            })
+    } else if metadata.is_file() {
+        (1, metadata.len())
+    } else {
+        (1, 0)
+    }
+}
+
+/// Copying reads a file to its end, but reading a FIFO blocks until something writes to it
+/// and a device may never end: only regular files, directories and links are copied.
+fn is_special(file_type: fs::FileType) -> bool {
+    !(file_type.is_file() || file_type.is_dir() || file_type.is_symlink())
+}
+
+fn special_file_error() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "not a regular file, it is not copied",
+    )
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+struct Worker {
+    kind: Kind,
+    events: mpsc::Sender<Event>,
+    replies: mpsc::Receiver<Reply>,
+    cancelled: Arc<AtomicBool>,
+    progress: Progress,
+    started: Instant,
+    last_sent: Instant,
+    resolve_all: Option<Resolution>,
+    report: Report,
+}
+
+impl Worker {
+    fn new(
+        kind: Kind,
+        events: mpsc::Sender<Event>,
+        replies: mpsc::Receiver<Reply>,
+        cancelled: Arc<AtomicBool>,
+    ) -> Self {
+        Worker {
+            kind,
+            events,
+            replies,
+            cancelled,
+            progress: Progress::default(),
+            started: Instant::now(),
+            last_sent: Instant::now(),
+            resolve_all: None,
+            report: Report::default(),
+        }
+    }
+
+    fn check_cancelled(&self) -> io::Result<()> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            Err(cancelled_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn send_progress(&mut self, force: bool) {
+        if force || self.last_sent.elapsed() >= PROGRESS_INTERVAL {
+            self.progress.elapsed = self.started.elapsed();
+            self.last_sent = Instant::now();
+            let _ = self.events.send(Event::Progress(self.progress.clone()));
+        }
+    }
+
+    fn start_file(&mut self, path: &Path, size: u64) {
+        self.progress.current = Some(path.to_path_buf());
+        self.progress.file_bytes_done = 0;
+        self.progress.file_bytes_total = size;
+        self.send_progress(false);
+    }
+
+    fn finish_file(&mut self) {
+        self.progress.files_done += 1;
+        self.send_progress(false);
+    }
+
+    /// Asks the user what to do with an existing destination, unless they already
+    /// decided for all conflicts.
+    fn resolve(&mut self, destination: &Path) -> io::Result<Resolution> {
+        if let Some(resolution) = self.resolve_all {
+            return Ok(resolution);
+        }
+
+        self.send_progress(true);
+        let _ = self.events.send(Event::Conflict(destination.to_path_buf()));
+        match self.replies.recv() {
+            Ok(Some((resolution, apply_to_all))) => {
+                if apply_to_all {
+                    self.resolve_all = Some(resolution);
+                }
+                Ok(resolution)
+            }
+            _ => Err(cancelled_error()),
+        }
+    }
+
+    fn copy_file(&mut self, source: &Path, destination: &Path, size: u64) -> io::Result<()> {
+        self.start_file(source, size);
+
+        let mut reader = fs::File::open(source)?;
+        let mut writer = fs::File::create(destination)?;
+        let mut buffer = vec![0; BUFFER_SIZE];
+
+        let result = loop {
+            if let Err(error) = self.check_cancelled() {
+                break Err(error);
+            }
+            let count = match reader.read(&mut buffer) {
+                Ok(0) => break Ok(()),
+                Ok(count) => count,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => break Err(error),
+            };
+            if let Err(error) = writer.write_all(&buffer[..count]) {
+                break Err(error);
+            }
+            self.progress.file_bytes_done += count as u64;
+            self.progress.bytes_done += count as u64;
+            self.send_progress(false);
+        };
+
+        if result.is_err() {
+            // do not leave a truncated copy behind
+            drop(writer);
+            let _ = fs::remove_file(destination);
+            return result;
+        }
+
+        writer.set_permissions(reader.metadata()?.permissions())?;
+        self.finish_file();
+        Ok(())
+    }
+
+    fn copy(&mut self, source: &Path, destination: &Path) -> io::Result<()> {
+        self.check_cancelled()?;
+        let metadata = fs::symlink_metadata(source)?;
+
+        if metadata.is_symlink() {
+            self.start_file(source, 0);
+            std::os::unix::fs::symlink(fs::read_link(source)?, destination)?;
+            self.finish_file();
+        } else if metadata.is_dir() {
+            self.start_file(source, 0);
+            fs::create_dir(destination)?;
+            self.finish_file();
+
+            for entry in fs::read_dir(source)? {
+                let entry = entry?;
+                if is_special(entry.file_type()?) {
+                    // left out, the rest of the directory is copied
+                    let error = special_file_error();
+                    self.report
+                        .errors
+                        .push(format!("{}: {error}", entry.path().display()));
+                    self.finish_file();
+                    continue;
+                }
+                self.copy(&entry.path(), &destination.join(entry.file_name()))?;
+            }
+            fs::set_permissions(destination, metadata.permissions())?;
+        } else if metadata.is_file() {
+            self.copy_file(source, destination, metadata.len())?;
+        } else {
+            return Err(special_file_error());
+        }
+        Ok(())
+    }
+
+    fn delete(&mut self, path: &Path) -> io::Result<()> {
+        self.check_cancelled()?;
+        let metadata = fs::symlink_metadata(path)?;
+        self.start_file(path, 0);
+
+        if metadata.is_dir() {
+            for entry in fs::read_dir(path)? {
+                self.delete(&entry?.path())?;
+            }
+            fs::remove_dir(path)?;
+        } else {
+            fs::remove_file(path)?;
+            self.progress.bytes_done += metadata.len();
+        }
+        self.finish_file();
+        Ok(())
+    }
+
+    fn trash(&mut self, path: &Path) -> io::Result<PathBuf> {
+        self.check_cancelled()?;
+        self.start_file(path, 0);
+
+        let (files, bytes) = measure(path);
+        let destination = trash::put(path)?;
+        self.progress.files_done += files;
+        self.progress.bytes_done += bytes;
+        self.send_progress(false);
+        Ok(destination)
+    }
+
+    fn transfer(&mut self, source: &Path, directory: &Path) -> io::Result<Option<PathBuf>> {
+        let name = source
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+        let mut destination = directory.join(name);
+
+        if destination == source {
+            match self.kind {
+                // pasting into the same directory makes a copy next to the original
+                Kind::Copy => destination = unique_path(&destination),
+                // moving there leaves it where it is, nothing to report or undo
+                _ => return Ok(None),
+            }
+        } else if destination.starts_with(source) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot copy a directory into itself",
+            ));
+        } else if source.starts_with(&destination) {
+            // overwriting would delete the source along with the directory containing it
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot replace a directory containing the source",
+            ));
+        } else if fs::symlink_metadata(&destination).is_ok() {
+            match self.resolve(&destination)? {
+                Resolution::Overwrite => {
+                    // overwriting a directory would delete everything in it
+                    if fs::symlink_metadata(&destination)?.is_dir() {
+                        return Err(io::Error::new(
+                            io::ErrorKind::IsADirectory,
+                            "cannot overwrite a directory",
+                        ));
+                    }
+                    remove(&destination)?
+                }
+                Resolution::Skip => return Ok(None),
+                Resolution::Rename => destination = unique_path(&destination),
+            }
+        }
+
+        if self.kind == Kind::Move {
+            self.move_entry(source, &destination)?;
+        } else if let Err(error) = self.copy(source, &destination) {
+            let _ = remove(&destination);
+            return Err(error);
+        }
+        Ok(Some(destination))
+    }
+
+    /// Renames `source` to `destination`, or copies and deletes it when they are on
+    /// different file systems.
+    fn move_entry(&mut self, source: &Path, destination: &Path) -> io::Result<()> {
+        match fs::rename(source, destination) {
+            Ok(()) => {
+                let (files, bytes) = measure(destination);
+                self.progress.files_done += files;
+                self.progress.bytes_done += bytes;
+                self.send_progress(false);
+                return Ok(());
+            }
+            Err(error) if error.kind() == io::ErrorKind::CrossesDevices => {}
+            Err(error) => return Err(error),
+        }
+
+        let errors = self.report.errors.len();
+        if let Err(error) = self.copy(source, destination) {
+            let _ = remove(destination);
+            return Err(error);
+        }
+        // what was left out of the copy would be lost
+        if self.report.errors.len() > errors {
+            return Err(io::Error::other(
+                "not everything could be copied, the source is kept",
+            ));
+        }
+        remove(source)
+    }
+
+    fn run(mut self, sources: Vec<PathBuf>, destination: Option<PathBuf>) {
+        for source in &sources {
+            let (files, bytes) = measure(source);
+            self.progress.files_total += files;
+            self.progress.bytes_total += bytes;
+        }
+        self.send_progress(true);
+
+        for source in sources {
+            let result = match (&self.kind, &destination) {
+                (Kind::Delete, _) => self.delete(&source).map(|()| Some(None)),
+                (Kind::Trash, _) => self.trash(&source).map(|path| Some(Some(path))),
+                (_, Some(directory)) => {
+                    let directory = directory.clone();
+                    self.transfer(&source, &directory)
+                        .map(|done| done.map(Some))
+                }
+                (_, None) => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "no destination",
+                )),
+            };
+
+            match result {
+                Ok(Some(destination)) => self.report.completed.push((source, destination)),
+                Ok(None) => self.report.skipped += 1,
+                Err(error)
+                    if error.kind() == io::ErrorKind::Interrupted
+                        || self.cancelled.load(Ordering::Relaxed) =>
+                {
+                    self.report.cancelled = true;
+                    break;
+                }
+                Err(error) => self
+                    .report
+                    .errors
+                    .push(format!("{}: {error}", source.display())),
+            }
+        }
+
+        self.send_progress(true);
+        let _ = self.events.send(Event::Finished(self.report));
+    }
+}
+
+/// Moves `source` to `destination` the way a move operation does, copying and deleting it
+/// when they are on different file systems, but in the calling thread and without asking
+/// about conflicts.
+pub fn move_entry(source: &Path, destination: &Path) -> io::Result<()> {
+    // nobody listens to the progress
+    let (events, _) = mpsc::channel();
+    let (_, replies) = mpsc::channel();
+    let mut worker = Worker::new(Kind::Move, events, replies, Arc::default());
+    worker.move_entry(source, destination)
+}
+
+/// A copy, move or delete running in a background thread.
+pub struct Operation {
+    pub kind: Kind,
+    pub progress: Progress,
+    /// An existing destination waiting for [`Operation::resolve`].
+    pub conflict: Option<PathBuf>,
+    events: mpsc::Receiver<Event>,
+    replies: mpsc::Sender<Reply>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Operation {
+    /// Starts processing `sources`, recursively for directories. Copies and moves go into
+    /// the `destination` directory.
+    pub fn start(kind: Kind, sources: Vec<PathBuf>, destination: Option<PathBuf>) -> Self {
+        let (events, worker_events) = mpsc::channel();
+        let (worker_replies, replies) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let worker = Worker::new(kind, events, replies, cancelled.clone());
+        std::thread::spawn(move || worker.run(sources, destination));
+
+        Operation {
+            kind,
+            progress: Progress::default(),
+            conflict: None,
+            events: worker_events,
+            replies: worker_replies,
+            cancelled,
+        }
+    }
+
+    /// Answers the pending conflict, optionally for all conflicts that follow.
+    pub fn resolve(&mut self, resolution: Resolution, apply_to_all: bool) {
+        if self.conflict.take().is_some() {
+            let _ = self.replies.send(Some((resolution, apply_to_all)));
+        }
+    }
+
+    /// Stops after the current file, which is removed if it was copied partially.
+    pub fn cancel(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        if self.conflict.take().is_some() {
+            let _ = self.replies.send(None);
+        }
+    }
+
+    /// Picks up progress made since the last call, returns the report once finished.
+    pub fn poll(&mut self) -> Option<Report> {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Event::Progress(progress) => self.progress = progress,
+                Event::Conflict(path) => self.conflict = Some(path),
+                Event::Finished(report) => return Some(report),
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait(mut operation: Operation, resolution: Resolution) -> Report {
+        loop {
+            if let Some(report) = operation.poll() {
+                return report;
+            }
+            if operation.conflict.is_some() {
+                operation.resolve(resolution, false);
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// The directory is removed when the returned guard is dropped.
+    fn setup() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("source/dir/nested")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join("source/file.txt"), "file").unwrap();
+        fs::write(root.join("source/dir/nested/deep"), "deep").unwrap();
+        std::os::unix::fs::symlink("nested", root.join("source/dir/link")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn copy_and_conflicts() {
+        let dir = setup();
+        let root = dir.path();
+        let sources = vec![root.join("source/dir"), root.join("source/file.txt")];
+        let target = root.join("target");
+
+        let report = wait(
+            Operation::start(Kind::Copy, sources.clone(), Some(target.clone())),
+            Resolution::Skip,
+        );
+        assert_eq!(report.completed.len(), 2);
+        assert_eq!(
+            fs::read_to_string(target.join("dir/nested/deep")).unwrap(),
+            "deep"
+        );
+        assert_eq!(
+            fs::read_link(target.join("dir/link")).unwrap(),
+            Path::new("nested")
+        );
+        assert!(root.join("source/file.txt").exists());
+
+        let report = wait(
+            Operation::start(Kind::Copy, sources.clone(), Some(target.clone())),
+            Resolution::Skip,
+        );
+        assert_eq!(report.skipped, 2);
+
+        let report = wait(
+            Operation::start(Kind::Copy, sources, Some(target.clone())),
+            Resolution::Rename,
+        );
+        assert_eq!(report.completed.len(), 2);
+        assert!(target.join("dir (1)/nested/deep").exists());
+        assert!(target.join("file (1).txt").exists());
+
+        // into the same directory
+        let report = wait(
+            Operation::start(
+                Kind::Copy,
+                vec![target.join("file.txt")],
+                Some(target.clone()),
+            ),
+            Resolution::Skip,
+        );
+        assert_eq!(report.completed.len(), 1);
+        assert!(target.join("file (2).txt").exists());
+
+        let report = wait(
+            Operation::start(
+                Kind::Copy,
+                vec![root.join("source")],
+                Some(root.join("source/dir")),
+            ),
+            Resolution::Skip,
+        );
+        assert_eq!(report.errors.len(), 1);
+    }
+
+    #[test]
+    fn move_and_delete() {
+        let dir = setup();
+        let root = dir.path();
+        let target = root.join("target");
+        fs::write(target.join("file.txt"), "old").unwrap();
+
+        let report = wait(
+            Operation::start(
+                Kind::Move,
+                vec![root.join("source/dir"), root.join("source/file.txt")],
+                Some(target.clone()),
+            ),
+            Resolution::Overwrite,
+        );
+        assert_eq!(report.completed.len(), 2);
+        assert_eq!(
+            report.completed[0],
+            (root.join("source/dir"), Some(target.join("dir")))
+        );
+        assert_eq!(fs::read_to_string(target.join("file.txt")).unwrap(), "file");
+        assert!(!root.join("source/dir").exists());
+
+        // into the same directory
+        let report = wait(
+            Operation::start(
+                Kind::Move,
+                vec![target.join("file.txt")],
+                Some(target.clone()),
+            ),
+            Resolution::Skip,
+        );
+        assert!(report.completed.is_empty());
+        assert_eq!(report.skipped, 1);
+        assert!(target.join("file.txt").exists());
+
+        let report = wait(
+            Operation::start(Kind::Delete, vec![target.join("dir")], None),
+            Resolution::Skip,
+        );
+        assert_eq!(report.completed, vec![(target.join("dir"), None)]);
+        assert!(!target.join("dir").exists());
+    }
+
+    #[test]
+    fn directory_conflicts() {
+        let dir = setup();
+        let root = dir.path();
+        fs::create_dir_all(root.join("target/dir/kept")).unwrap();
+
+        // a directory is not overwritten, neither is one containing the source
+        let report = wait(
+            Operation::start(
+                Kind::Move,
+                vec![root.join("source/dir")],
+                Some(root.join("target")),
+            ),
+            Resolution::Overwrite,
+        );
+        assert_eq!(report.errors.len(), 1);
+        assert!(root.join("target/dir/kept").exists());
+        assert!(root.join("source/dir/nested/deep").exists());
+
+        fs::create_dir_all(root.join("a/x/x")).unwrap();
+        let report = wait(
+            Operation::start(Kind::Move, vec![root.join("a/x/x")], Some(root.join("a"))),
+            Resolution::Overwrite,
+        );
+        assert_eq!(report.errors.len(), 1);
+        assert!(root.join("a/x/x").exists());
+    }
+
+    #[test]
+    fn special_files() {
+        let dir = setup();
+        let root = dir.path();
+        let fifo = root.join("source/dir/fifo");
+        let status = std::process::Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        // copying the FIFO would block, it is left out of the directory
+        let report = wait(
+            Operation::start(
+                Kind::Copy,
+                vec![root.join("source/dir"), fifo.clone()],
+                Some(root.join("target")),
+            ),
+            Resolution::Skip,
+        );
+        assert_eq!(report.completed.len(), 1);
+        assert_eq!(report.errors.len(), 2);
+        assert!(root.join("target/dir/nested/deep").exists());
+        assert!(fs::symlink_metadata(root.join("target/dir/fifo")).is_err());
+        assert!(fs::symlink_metadata(root.join("target/fifo")).is_err());
+    }
+
+    #[test]
+    fn progress_ratio() {
+        let progress = Progress {
+            bytes_done: 25,
+            bytes_total: 100,
+            ..Progress::default()
+        };
+        assert_eq!(progress.ratio(), 0.25);
+        assert_eq!(Progress::default().ratio(), 0.0);
+    }
+}
@@ -0,0 +1,61 @@
+use std::ffi::OsString;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"-_.~/".contains(&byte)
+}
+
+/// Encodes a path like in URLs, the way `.trashinfo` files store it, so that spaces, line
+/// breaks and bytes that are not UTF-8 survive in text files.
+pub fn percent_encode(path: &Path) -> String {
+    path.as_os_str()
+        .as_bytes()
+        .iter()
+        .map(|&byte| {
+            if is_unreserved(byte) {
+                (byte as char).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect()
+}
+
+/// Decodes what `percent_encode` produced, keeping invalid escapes as they are.
+pub fn percent_decode(encoded: &str) -> PathBuf {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    PathBuf::from(OsString::from_vec(decoded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_encoding() {
+        let path = Path::new("/home/user/a file%.txt");
+        assert_eq!(percent_encode(path), "/home/user/a%20file%25.txt");
+        assert_eq!(percent_decode(&percent_encode(path)), path);
+        assert_eq!(percent_decode("/bad%zz"), Path::new("/bad%zz"));
+    }
+}
@@ -0,0 +1,343 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+use tui::style::*;
+use tui::text::Spans;
+use tui::widgets::*;
+
+use crate::config::ViewOptions;
+use crate::model::{self, details, Item};
+
+const TAB_WIDTH: usize = 4;
+const HEX_BYTES_PER_LINE: usize = 16;
+const LOADING_PLACEHOLDER: &str = "Loading...";
+
+pub enum Content {
+    Text(Vec<String>),
+    Directory(Vec<Item>),
+    Hex(Vec<String>),
+    Message(String),
+}
+
+fn paragraph(lines: &[String], style: Style) -> Paragraph<'_> {
+    let lines: Vec<_> = lines
+        .iter()
+        .map(|line| Spans::from(line.as_str()))
+        .collect();
+    Paragraph::new(lines).style(style)
+}
+
+/// Draws a preview, or a loading placeholder if there is no content yet.
+pub fn render<B: tui::backend::Backend>(
+    f: &mut tui::Frame<B>,
+    area: tui::layout::Rect,
+    block: Block,
+    content: Option<&Content>,
+) {
+    match content {
+        Some(Content::Text(lines)) => {
+            f.render_widget(paragraph(lines, Style::default()).block(block), area)
+        }
+        Some(Content::Hex(lines)) => f.render_widget(
+            paragraph(lines, Style::default().fg(Color::Gray)).block(block),
+            area,
+        ),
+        Some(Content::Directory(items)) => {
+            let list_items: Vec<_> = items
+                .iter()
+                .map(|item| ListItem::new(item.name.as_str()).style(model::item_style(item)))
+                .collect();
+            f.render_widget(List::new(list_items).block(block), area)
+        }
+        Some(Content::Message(message)) => f.render_widget(
+            Paragraph::new(message.as_str())
+                .style(Style::default().add_modifier(Modifier::ITALIC))
+                .block(block),
+            area,
+        ),
+        None => f.render_widget(
+            Paragraph::new(LOADING_PLACEHOLDER)
+                .style(Style::default().add_modifier(Modifier::ITALIC))
+                .block(block),
+            area,
+        ),
+    }
+}
+
+struct Request {
+    path: PathBuf,
+    options: ViewOptions,
+}
+
+/// Loads previews in a background thread, so that moving over large files does not block the UI.
+///
+/// Only the most recent request is served, older pending ones are dropped.
+pub struct Previewer {
+    requests: mpsc::Sender<Request>,
+    results: mpsc::Receiver<(PathBuf, Content)>,
+    requested: Option<PathBuf>,
+    current: Option<(PathBuf, Content)>,
+}
+
+impl Previewer {
+    pub fn new() -> Self {
+        let (requests, worker_requests) = mpsc::channel::<Request>();
+        let (worker_results, results) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            while let Ok(mut request) = worker_requests.recv() {
+                while let Ok(newer) = worker_requests.try_recv() {
+                    request = newer;
+                }
+
+                let content = load(&request.path, &request.options);
+                if worker_results.send((request.path, content)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Previewer {
+            requests,
+            results,
+            requested: None,
+            current: None,
+        }
+    }
+
+    /// Asks for a preview of `path` unless it is already loaded or being loaded, returns
+    /// whether it was asked for.
+    pub fn request(&mut self, path: &Path, options: &ViewOptions) -> bool {
+        if self.requested.as_deref() == Some(path) {
+            return false;
+        }
+
+        self.requested = Some(path.to_path_buf());
+        let _ = self.requests.send(Request {
+            path: path.to_path_buf(),
+            options: options.clone(),
+        });
+        true
+    }
+
+    /// Forgets the loaded preview, so that the next request reloads it.
+    pub fn invalidate(&mut self) {
+        self.requested = None;
+    }
+
+    /// Picks up previews loaded since the last call, returns whether there were any.
+    pub fn poll(&mut self) -> bool {
+        let mut loaded = false;
+        while let Ok(result) = self.results.try_recv() {
+            self.current = Some(result);
+            loaded = true;
+        }
+        loaded
+    }
+
+    /// The preview of the last requested path, `None` while it is loading.
+    pub fn content(&self) -> Option<&Content> {
+        match (&self.requested, &self.current) {
+            (Some(requested), Some((path, content))) if requested == path => Some(content),
+            _ => None,
+        }
+    }
+}
+
+fn expand_tabs(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = TAB_WIDTH - result.chars().count() % TAB_WIDTH;
+            result.extend(std::iter::repeat_n(' ', spaces));
+        } else if !c.is_control() {
+            result.push(c);
+        }
+    }
+    result
+}
+
+fn hex_dump(buffer: &[u8], max_lines: usize) -> Vec<String> {
+    buffer
+        .chunks(HEX_BYTES_PER_LINE)
+        .take(max_lines)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let hex: Vec<_> = chunk.iter().map(|byte| format!("{byte:02x}")).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|&byte| {
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!(
+                "{:08x}  {:<width$}  |{}|",
+                index * HEX_BYTES_PER_LINE,
+                hex.join(" "),
+                ascii,
+                width = HEX_BYTES_PER_LINE * 3 - 1
+            )
+        })
+        .collect()
+}
+
+fn load_directory(path: &Path, options: &ViewOptions) -> Content {
+    match std::fs::read_dir(path) {
+        Ok(entries) => {
+            let entries: Vec<_> = entries.filter_map(Result::ok).collect();
+            let mut items = model::get_items(&entries, options);
+            items.truncate(options.preview.max_lines);
+            Content::Directory(items)
+        }
+        Err(error) => Content::Message(error.to_string()),
+    }
+}
+
+/// Describes what is neither a regular file nor a directory, as it is not read: reading a
+/// FIFO blocks until something writes to it, and a device may never end.
+fn describe_special_file(file_type: std::fs::FileType) -> &'static str {
+    use std::os::unix::fs::FileTypeExt;
+
+    if file_type.is_fifo() {
+        "<named pipe>"
+    } else if file_type.is_socket() {
+        "<socket>"
+    } else if file_type.is_char_device() {
+        "<character device>"
+    } else if file_type.is_block_device() {
+        "<block device>"
+    } else {
+        "<special file>"
+    }
+}
+
+fn load_file(path: &Path, options: &ViewOptions) -> Content {
+    let max_bytes = options.preview.max_bytes;
+    let max_lines = options.preview.max_lines;
+
+    match std::fs::metadata(path) {
+        Ok(metadata) if !metadata.is_file() => {
+            return Content::Message(String::from(describe_special_file(metadata.file_type())))
+        }
+        Ok(_) => {}
+        Err(error) => return Content::Message(error.to_string()),
+    }
+
+    let mut buffer = Vec::new();
+    if let Err(error) =
+        std::fs::File::open(path).and_then(|file| file.take(max_bytes).read_to_end(&mut buffer))
+    {
+        return Content::Message(error.to_string());
+    }
+
+    if buffer.is_empty() {
+        return Content::Message(String::from("<empty>"));
+    }
+
+    let is_text = match infer::get(&buffer) {
+        Some(kind) => kind.matcher_type() == infer::MatcherType::TEXT,
+        None => details::is_text(&buffer),
+    };
+
+    if is_text {
+        let lines = String::from_utf8_lossy(&buffer)
+            .lines()
+            .take(max_lines)
+            .map(expand_tabs)
+            .collect();
+        Content::Text(lines)
+    } else {
+        Content::Hex(hex_dump(&buffer, max_lines))
+    }
+}
+
+/// Reads the preview of a file or directory, reading at most `max_bytes` of a file.
+pub fn load(path: &Path, options: &ViewOptions) -> Content {
+    if path.is_dir() {
+        load_directory(path, options)
+    } else {
+        load_file(path, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_dump_format() {
+        let lines = hex_dump(b"0123456789abcdef\x00\x01 z", 10);
+        assert_eq!(
+            lines,
+            vec![
+                "00000000  30 31 32 33 34 35 36 37 38 39 61 62 63 64 65 66  |0123456789abcdef|",
+                "00000010  00 01 20 7a                                      |.. z|",
+            ]
+        );
+        assert_eq!(hex_dump(&[0; 64], 2).len(), 2);
+    }
+
+    #[test]
+    fn expand_tabs_to_columns() {
+        assert_eq!(expand_tabs("a\tbc\td"), "a   bc  d");
+        assert_eq!(expand_tabs("\r"), "");
+    }
+
+    #[test]
+    fn load_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("dir/child")).unwrap();
+        std::fs::write(root.join("text"), "line 1\nline 2\nline 3\n").unwrap();
+        std::fs::write(root.join("binary"), [0x7f, b'E', b'L', b'F', 0, 0]).unwrap();
+
+        let mut options = ViewOptions::default();
+        options.preview.max_lines = 2;
+
+        match load(&root.join("text"), &options) {
+            Content::Text(lines) => assert_eq!(lines, vec!["line 1", "line 2"]),
+            _ => panic!("expected text"),
+        }
+        match load(&root.join("binary"), &options) {
+            Content::Hex(lines) => assert_eq!(lines.len(), 1),
+            _ => panic!("expected hex dump"),
+        }
+        match load(&root.join("dir"), &options) {
+            Content::Directory(items) => assert_eq!(items[0].name, "child"),
+            _ => panic!("expected directory"),
+        }
+
+        options.preview.max_bytes = 3;
+        match load(&root.join("text"), &options) {
+            Content::Text(lines) => assert_eq!(lines, vec!["lin"]),
+            _ => panic!("expected text"),
+        }
+    }
+
+    #[test]
+    fn special_files_are_not_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let fifo = dir.path().join("fifo");
+        let status = std::process::Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let options = ViewOptions::default();
+        // opening the FIFO would block until something writes to it
+        match load(&fifo, &options) {
+            Content::Message(message) => assert_eq!(message, "<named pipe>"),
+            _ => panic!("expected a message"),
+        }
+        match load(Path::new("/dev/zero"), &options) {
+            Content::Message(message) => assert_eq!(message, "<character device>"),
+            _ => panic!("expected a message"),
+        }
+    }
+}
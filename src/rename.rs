@@ -0,0 +1,456 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::journal::{Change, ChangeKind};
+use crate::terminal;
+
+fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') || name.contains('\0') {
+        Err(format!("Invalid name '{name}'"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Turns pairs of entries and their new names into renames within their directories,
+/// leaving out unchanged names. Fails without touching anything if a new name is
+/// invalid, used twice in a directory or taken by an entry that is not renamed itself,
+/// or if an entry to rename has a name that is not valid UTF-8.
+pub fn prepare(names: &[(PathBuf, String)]) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let mut changed = Vec::new();
+    for (path, new) in names {
+        let name = path.file_name().unwrap_or_default();
+        // names are edited as shown, which is lossy for names that are not UTF-8
+        let shown = name.to_string_lossy();
+        if shown == new.as_str() {
+            continue;
+        }
+        if name.to_str().is_none() {
+            return Err(format!(
+                "'{shown}' is not valid UTF-8 and cannot be renamed"
+            ));
+        }
+        changed.push((path, new));
+    }
+    let sources: HashSet<&Path> = changed.iter().map(|(path, _)| path.as_path()).collect();
+
+    let mut renames = Vec::new();
+    let mut targets = HashSet::new();
+    for (path, new) in changed {
+        check_name(new)?;
+        let target = path.with_file_name(new);
+        if !targets.insert(target.clone()) {
+            return Err(format!("'{new}' would be used more than once"));
+        }
+        if !sources.contains(target.as_path()) && fs::symlink_metadata(&target).is_ok() {
+            return Err(format!("'{new}' already exists"));
+        }
+        renames.push((path.clone(), target));
+    }
+    Ok(renames)
+}
+
+/// A free name next to `path` to park it under while its own name is still taken.
+fn temporary_path(path: &Path, taken: &HashSet<&Path>) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    (0..)
+        .map(|n| path.with_file_name(format!(".{name}.rsfm-rename-{n}")))
+        .find(|temporary| {
+            !taken.contains(temporary.as_path()) && fs::symlink_metadata(temporary).is_err()
+        })
+        .unwrap()
+}
+
+/// Orders `renames` so that no entry is renamed to a name another one still has.
+/// Entries in a cycle, such as a->b and b->a, first move to a temporary name.
+pub fn plan(renames: &[(PathBuf, PathBuf)]) -> Vec<(PathBuf, PathBuf)> {
+    let taken: HashSet<&Path> = renames
+        .iter()
+        .flat_map(|(from, to)| [from.as_path(), to.as_path()])
+        .collect();
+    let mut pending: Vec<(PathBuf, PathBuf)> = renames
+        .iter()
+        .filter(|(from, to)| from != to)
+        .cloned()
+        .collect();
+    let mut steps = Vec::new();
+
+    while !pending.is_empty() {
+        let free = pending
+            .iter()
+            .position(|(_, to)| pending.iter().all(|(from, _)| from != to));
+        match free {
+            Some(index) => steps.push(pending.remove(index)),
+            // only cycles are left, break one of them up
+            None => {
+                let temporary = temporary_path(&pending[0].0, &taken);
+                let from = std::mem::replace(&mut pending[0].0, temporary.clone());
+                steps.push((from, temporary));
+            }
+        }
+    }
+    steps
+}
+
+/// Performs the steps of [`plan`], stopping at the first error. Every step done so far
+/// is added to `changes`, so that it can be undone.
+pub fn apply(steps: &[(PathBuf, PathBuf)], changes: &mut Vec<Change>) -> io::Result<()> {
+    for (from, to) in steps {
+        // something may have appeared since the renames were checked
+        if fs::symlink_metadata(to).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("'{}' already exists", to.display()),
+            ));
+        }
+        fs::rename(from, to).map_err(|error| {
+            io::Error::new(error.kind(), format!("{}: {error}", from.display()))
+        })?;
+        changes.push(Change::new(ChangeKind::Move, from.clone(), to.clone())?);
+    }
+    Ok(())
+}
+
+/// Lets the user edit `names` in `$VISUAL` or `$EDITOR`, one per line, and returns
+/// the edited names in the same order.
+pub fn edit_names(names: &[String]) -> io::Result<Vec<String>> {
+    if names.iter().any(|name| name.contains('\n')) {
+        return Err(io::Error::other(
+            "Cannot edit names containing a line break",
+        ));
+    }
+
+    // a random name only the user can read and write, removed when dropped
+    let mut file = tempfile::Builder::new().prefix("rsfm-rename-").tempfile()?;
+    file.write_all((names.join("\n") + "\n").as_bytes())?;
+    file.flush()?;
+
+    let status = terminal::edit(file.path(), None);
+    let contents = fs::read_to_string(file.path());
+    drop(file);
+
+    if !status?.success() {
+        return Err(io::Error::other("The editor failed, nothing was renamed"));
+    }
+    let edited: Vec<String> = contents?.lines().map(String::from).collect();
+    if edited.len() != names.len() {
+        return Err(io::Error::other(format!(
+            "Expected {} names, found {}, nothing was renamed",
+            names.len(),
+            edited.len()
+        )));
+    }
+    Ok(edited)
+}
+
+/// A piece of a rename template.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Part {
+    Text(String),
+    /// The name without its extension.
+    Name,
+    /// The extension including its dot, empty if there is none.
+    Extension,
+    /// The position of the entry among the renamed ones, starting at 1.
+    Counter {
+        width: usize,
+        zero_padded: bool,
+    },
+    /// The modification time, as a strftime format.
+    Modified(String),
+}
+
+const DEFAULT_MODIFIED_FORMAT: &str = "%Y-%m-%d";
+
+fn parse_placeholder(placeholder: &str) -> Result<Part, String> {
+    let (name, spec) = match placeholder.split_once(':') {
+        Some((name, spec)) => (name, Some(spec)),
+        None => (placeholder, None),
+    };
+    match (name, spec) {
+        ("name", None) => Ok(Part::Name),
+        ("ext", None) => Ok(Part::Extension),
+        ("n", None) => Ok(Part::Counter {
+            width: 0,
+            zero_padded: false,
+        }),
+        ("n", Some(spec)) => match spec.parse() {
+            Ok(width) => Ok(Part::Counter {
+                width,
+                zero_padded: spec.starts_with('0'),
+            }),
+            Err(_) => Err(format!("Invalid counter width '{spec}'")),
+        },
+        ("mtime", spec) => {
+            use chrono::format::{Item, StrftimeItems};
+            let format = spec.unwrap_or(DEFAULT_MODIFIED_FORMAT);
+            if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                return Err(format!("Invalid time format '{format}'"));
+            }
+            Ok(Part::Modified(format.to_string()))
+        }
+        _ => Err(format!("Unknown placeholder '{{{placeholder}}}'")),
+    }
+}
+
+/// Parses a template such as `{name}_{n:03}{ext}`, `{{` and `}}` stand for braces.
+fn parse_template(template: &str) -> Result<Vec<Part>, String> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let rest = chars.as_str();
+                let end = rest
+                    .find('}')
+                    .ok_or_else(|| String::from("Missing '}' in the template"))?;
+                if !text.is_empty() {
+                    parts.push(Part::Text(std::mem::take(&mut text)));
+                }
+                parts.push(parse_placeholder(&rest[..end])?);
+                chars = rest[end + 1..].chars();
+            }
+            '}' => return Err(String::from("Unexpected '}' in the template")),
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        parts.push(Part::Text(text));
+    }
+    Ok(parts)
+}
+
+/// How the rename dialog makes up new names.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// Replaces every match of the expression, `$1` and `${name}` refer to groups.
+    Regex(regex::Regex, String),
+    Template(Vec<Part>),
+}
+
+impl Pattern {
+    /// Parses `expression/replacement`. Names cannot contain a slash, so the first one
+    /// separates the two. Without a replacement, nothing is renamed yet.
+    pub fn regex(text: &str) -> Result<Self, String> {
+        let (expression, replacement) = match text.split_once('/') {
+            Some((expression, replacement)) if !expression.is_empty() => (expression, replacement),
+            _ => return Ok(Pattern::Template(Vec::new())),
+        };
+        regex::Regex::new(expression)
+            .map(|regex| Pattern::Regex(regex, replacement.to_string()))
+            .map_err(|error| crate::model::filter::regex_error(&error))
+    }
+
+    pub fn template(text: &str) -> Result<Self, String> {
+        parse_template(text).map(Pattern::Template)
+    }
+
+    /// The new name of `name`, the `index`th of the renamed entries. An empty template
+    /// keeps the name.
+    pub fn apply(&self, name: &str, index: usize, modified: Option<SystemTime>) -> String {
+        let parts = match self {
+            Pattern::Regex(regex, replacement) => {
+                return regex.replace_all(name, replacement.as_str()).to_string();
+            }
+            Pattern::Template(parts) if parts.is_empty() => return name.to_string(),
+            Pattern::Template(parts) => parts,
+        };
+
+        // the same split as Item::extension
+        let (stem, extension) = match name.rfind('.') {
+            Some(0) | None => (name, ""),
+            Some(dot) => name.split_at(dot),
+        };
+        let mut result = String::new();
+        for part in parts {
+            match part {
+                Part::Text(text) => result += text,
+                Part::Name => result += stem,
+                Part::Extension => result += extension,
+                Part::Counter { width, zero_padded } if *zero_padded => {
+                    result += &format!("{:0width$}", index + 1)
+                }
+                Part::Counter { width, .. } => result += &format!("{:width$}", index + 1),
+                Part::Modified(format) => {
+                    if let Some(time) = modified {
+                        result += &chrono::DateTime::<chrono::Local>::from(time)
+                            .format(format)
+                            .to_string();
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(root: &Path, names: &[(&str, &str)]) -> Vec<(PathBuf, String)> {
+        names
+            .iter()
+            .map(|(old, new)| (root.join(old), new.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn prepare_checks_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for name in ["a", "b", "c"] {
+            fs::write(root.join(name), name).unwrap();
+        }
+
+        assert_eq!(
+            prepare(&pairs(root, &[("a", "a"), ("b", "d")])),
+            Ok(vec![(root.join("b"), root.join("d"))])
+        );
+        assert!(prepare(&pairs(root, &[("a", "x/y")])).is_err());
+        assert!(prepare(&pairs(root, &[("a", "")])).is_err());
+        assert!(prepare(&pairs(root, &[("a", "d"), ("b", "d")])).is_err());
+        // 'c' keeps its name
+        assert!(prepare(&pairs(root, &[("a", "c")])).is_err());
+        // 'b' makes room
+        assert!(prepare(&pairs(root, &[("a", "b"), ("b", "d")])).is_ok());
+
+        // entries nested in a tree are renamed within their own directories
+        fs::create_dir(root.join("dir")).unwrap();
+        fs::write(root.join("dir/a.tar.gz"), "").unwrap();
+        assert_eq!(
+            prepare(&pairs(root, &[("dir/a.tar.gz", "d"), ("a", "d")])),
+            Ok(vec![
+                (root.join("dir/a.tar.gz"), root.join("dir/d")),
+                (root.join("a"), root.join("d"))
+            ])
+        );
+    }
+
+    #[test]
+    fn names_not_in_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("a"), "").unwrap();
+        fs::write(root.join(std::ffi::OsStr::from_bytes(b"bad\xff")), "").unwrap();
+
+        // the names of the listing, as written to the editor
+        let options = crate::config::ViewOptions::default();
+        let items = crate::navigation::read_items(root, &options, &mut Vec::new()).unwrap();
+        let mut names: Vec<_> = items
+            .iter()
+            .map(|item| (item.path.clone(), item.name.clone()))
+            .collect();
+        assert_eq!(prepare(&names), Ok(Vec::new()));
+
+        // 'a' could be renamed, the other one cannot
+        let index = names.iter().position(|(_, name)| name == "a").unwrap();
+        names[index].1 = String::from("b");
+        assert_eq!(prepare(&names).unwrap().len(), 1);
+        names[1 - index].1 = String::from("good");
+        assert!(prepare(&names).is_err());
+    }
+
+    #[test]
+    fn cycles() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for name in ["a", "b", "c", "d"] {
+            fs::write(root.join(name), name).unwrap();
+        }
+
+        // a swap and a chain
+        let renames = prepare(&pairs(
+            root,
+            &[("a", "b"), ("b", "a"), ("c", "d"), ("d", "e")],
+        ))
+        .unwrap();
+        let steps = plan(&renames);
+        assert_eq!(steps.len(), 5);
+
+        let mut changes = Vec::new();
+        apply(&steps, &mut changes).unwrap();
+        assert_eq!(fs::read_to_string(root.join("a")).unwrap(), "b");
+        assert_eq!(fs::read_to_string(root.join("b")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(root.join("d")).unwrap(), "c");
+        assert_eq!(fs::read_to_string(root.join("e")).unwrap(), "d");
+        assert!(!root.join("c").exists());
+
+        // a three-way cycle
+        let renames = prepare(&pairs(root, &[("a", "b"), ("b", "e"), ("e", "a")])).unwrap();
+        changes.clear();
+        apply(&plan(&renames), &mut changes).unwrap();
+        assert_eq!(fs::read_to_string(root.join("b")).unwrap(), "b");
+        assert_eq!(fs::read_to_string(root.join("e")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(root.join("a")).unwrap(), "d");
+        assert_eq!(fs::read_dir(root).unwrap().count(), 4);
+
+        // the journal undoes it in one go
+        let mut journal = crate::journal::Journal::open(10, None);
+        journal.record(crate::journal::Entry {
+            description: String::from("rename"),
+            changes,
+        });
+        journal.undo().unwrap();
+        assert_eq!(fs::read_to_string(root.join("a")).unwrap(), "b");
+        assert_eq!(fs::read_to_string(root.join("b")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(root.join("e")).unwrap(), "d");
+    }
+
+    #[test]
+    fn patterns() {
+        let template = Pattern::template("{name}_{n:03}{ext}").unwrap();
+        assert_eq!(template.apply("photo.jpg", 0, None), "photo_001.jpg");
+        assert_eq!(template.apply(".bashrc", 11, None), ".bashrc_012");
+        assert_eq!(
+            Pattern::template("{{{n:2}}}").unwrap().apply("x", 4, None),
+            "{ 5}"
+        );
+
+        let modified = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(86400 * 365);
+        let expected = chrono::DateTime::<chrono::Local>::from(modified)
+            .format("%Y")
+            .to_string();
+        assert_eq!(
+            Pattern::template("{mtime:%Y}-{name}")
+                .unwrap()
+                .apply("a.txt", 0, Some(modified)),
+            format!("{expected}-a")
+        );
+        assert_eq!(Pattern::template("").unwrap().apply("a", 0, None), "a");
+
+        assert!(Pattern::template("{size}").is_err());
+        assert!(Pattern::template("{name").is_err());
+        assert!(Pattern::template("name}").is_err());
+        assert!(Pattern::template("{n:x}").is_err());
+        assert!(Pattern::template("{mtime:%Q}").is_err());
+
+        let regex = Pattern::regex(r"(\w+)-(\d+)/$2-$1").unwrap();
+        assert_eq!(regex.apply("img-12.png", 0, None), "12-img.png");
+        assert_eq!(
+            Pattern::regex("a/o").unwrap().apply("banana", 0, None),
+            "bonono"
+        );
+        // still being typed
+        assert_eq!(
+            Pattern::regex("ban").unwrap().apply("banana", 0, None),
+            "banana"
+        );
+        assert!(Pattern::regex("(/").is_err());
+    }
+}
@@ -0,0 +1,264 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::config::{self, ConfigError, ConfigWarning, ViewOptions};
+use crate::terminal;
+
+/// Something a script asked the file manager to do, applied once the command returns.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Request {
+    ChangeDir(PathBuf),
+    Notify(String),
+    /// The terminal was handed over to a child process and has to be redrawn.
+    Redraw,
+}
+
+/// What the API functions see and produce while a command is running.
+#[derive(Default)]
+struct State {
+    cwd: PathBuf,
+    selected: Option<PathBuf>,
+    marked: Vec<PathBuf>,
+    requests: Vec<Request>,
+}
+
+/// The Lua state the configuration was executed in. It is kept alive for the whole
+/// session, so that commands defined in `rsfm.commands` can be run later.
+///
+/// Scripts talk to the file manager through functions in the `rsfm` table:
+/// `rsfm.cwd()`, `rsfm.selected()`, `rsfm.marked()`, `rsfm.cd(path)`,
+/// `rsfm.notify(message)` and `rsfm.shell(command)`.
+pub struct Script {
+    lua: rlua::Lua,
+    state: Arc<Mutex<State>>,
+}
+
+/// The functions `register_api` adds to the `rsfm` table, which the configuration
+/// check leaves alone.
+pub const API_FUNCTIONS: [&str; 6] = ["cwd", "selected", "marked", "cd", "notify", "shell"];
+
+fn register_api(ctx: rlua::Context, state: &Arc<Mutex<State>>) -> rlua::Result<()> {
+    let rsfm = ctx.create_table()?;
+
+    let cwd_state = state.clone();
+    rsfm.set(
+        "cwd",
+        ctx.create_function(move |_, ()| Ok(cwd_state.lock().unwrap().cwd.display().to_string()))?,
+    )?;
+
+    let selected_state = state.clone();
+    rsfm.set(
+        "selected",
+        ctx.create_function(move |_, ()| {
+            let state = selected_state.lock().unwrap();
+            Ok(state
+                .selected
+                .as_ref()
+                .map(|path| path.display().to_string()))
+        })?,
+    )?;
+
+    let marked_state = state.clone();
+    rsfm.set(
+        "marked",
+        ctx.create_function(move |_, ()| {
+            let state = marked_state.lock().unwrap();
+            Ok(state
+                .marked
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>())
+        })?,
+    )?;
+
+    let cd_state = state.clone();
+    rsfm.set(
+        "cd",
+        ctx.create_function(move |_, path: String| {
+            let mut state = cd_state.lock().unwrap();
+            let path = state.cwd.join(config::expand_home(&path));
+            if !path.is_dir() {
+                return Err(rlua::Error::RuntimeError(format!(
+                    "'{}' is not a directory",
+                    path.display()
+                )));
+            }
+            // later calls of rsfm.cwd() see the new directory
+            state.cwd = path.clone();
+            state.requests.push(Request::ChangeDir(path));
+            Ok(())
+        })?,
+    )?;
+
+    let notify_state = state.clone();
+    rsfm.set(
+        "notify",
+        ctx.create_function(move |_, message: String| {
+            let mut state = notify_state.lock().unwrap();
+            state.requests.push(Request::Notify(message));
+            Ok(())
+        })?,
+    )?;
+
+    let shell_state = state.clone();
+    rsfm.set(
+        "shell",
+        ctx.create_function(move |_, command: String| {
+            let cwd = shell_state.lock().unwrap().cwd.clone();
+            let status = terminal::suspended(|| {
+                std::process::Command::new("sh")
+                    .arg("-c")
+                    .arg(&command)
+                    .current_dir(&cwd)
+                    .status()
+            })
+            .and_then(|status| status)
+            .map_err(rlua::Error::external)?;
+
+            shell_state.lock().unwrap().requests.push(Request::Redraw);
+            // nil when killed by a signal
+            Ok(status.code())
+        })?,
+    )?;
+
+    ctx.globals().set("rsfm", rsfm)
+}
+
+impl Script {
+    pub fn new() -> Result<Self, ConfigError> {
+        let lua = rlua::Lua::new();
+        let state = Arc::new(Mutex::new(State::default()));
+
+        lua.context(|ctx| register_api(ctx, &state))
+            .map_err(|error| ConfigError::from_lua(None, &error))?;
+
+        Ok(Script { lua, state })
+    }
+
+    /// Executes the configuration files, see [`config::read_config`].
+    pub fn read_config(
+        &self,
+        paths: &[PathBuf],
+    ) -> Result<(ViewOptions, Vec<ConfigWarning>), ConfigError> {
+        config::read_config(&self.lua, paths)
+    }
+
+    /// Runs the command `name` from `rsfm.commands` and returns what it asked for, along
+    /// with the error it stopped at if any. Requests made before an error are returned
+    /// too, as what they stand for may already have happened.
+    ///
+    /// `marked` is what `rsfm.marked()` returns: the marked entries, or the selected one
+    /// when nothing is marked.
+    pub fn run_command(
+        &mut self,
+        name: &str,
+        cwd: &Path,
+        selected: Option<&Path>,
+        marked: &[PathBuf],
+    ) -> (Vec<Request>, Option<ConfigError>) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.cwd = cwd.to_path_buf();
+            state.selected = selected.map(Path::to_path_buf);
+            state.marked = marked.to_vec();
+            state.requests.clear();
+        }
+
+        let result = self.lua.context(|ctx| {
+            let rsfm: rlua::Table = ctx.globals().get("rsfm")?;
+            let commands: rlua::Table = rsfm.get("commands")?;
+            let command: rlua::Function = commands.get(name)?;
+            command.call::<_, ()>(())
+        });
+
+        let requests = std::mem::take(&mut self.state.lock().unwrap().requests);
+        let error = result
+            .err()
+            .map(|error| ConfigError::from_lua(None, &error));
+        (requests, error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("dir")).unwrap();
+        std::fs::write(
+            root.join("config.lua"),
+            r#"
+            rsfm.commands = {
+                into_dir = function()
+                    rsfm.cd("dir")
+                    rsfm.notify(rsfm.cwd())
+                end,
+                show_selected = function()
+                    rsfm.notify(rsfm.selected() or "nothing")
+                end,
+                count_marked = function()
+                    rsfm.notify(tostring(#rsfm.marked()))
+                end,
+                missing = function()
+                    rsfm.cd("missing")
+                end,
+                notify_and_fail = function()
+                    rsfm.notify("before")
+                    error("failed")
+                end,
+            }
+            rsfm.keys = { x = "into_dir" }
+            "#,
+        )
+        .unwrap();
+
+        let mut script = Script::new().unwrap();
+        let (options, warnings) = script.read_config(&[root.join("config.lua")]).unwrap();
+        assert_eq!(warnings, vec![]);
+        assert!(matches!(
+            options.keys.lookup(&crate::config::keys::parse_sequence("x").unwrap()),
+            crate::config::keys::Lookup::Command(name) if name == "into_dir"
+        ));
+
+        assert_eq!(
+            script.run_command("into_dir", root, None, &[]),
+            (
+                vec![
+                    Request::ChangeDir(root.join("dir")),
+                    Request::Notify(root.join("dir").display().to_string()),
+                ],
+                None
+            )
+        );
+        assert_eq!(
+            script.run_command("show_selected", root, None, &[]),
+            (vec![Request::Notify("nothing".to_string())], None)
+        );
+        assert_eq!(
+            script.run_command("show_selected", root, Some(&root.join("dir")), &[]),
+            (
+                vec![Request::Notify(root.join("dir").display().to_string())],
+                None
+            )
+        );
+        assert_eq!(
+            script.run_command(
+                "count_marked",
+                root,
+                None,
+                &[root.join("a"), root.join("b")]
+            ),
+            (vec![Request::Notify("2".to_string())], None)
+        );
+        assert!(script.run_command("missing", root, None, &[]).1.is_some());
+        assert!(script.run_command("undefined", root, None, &[]).1.is_some());
+
+        // what was asked for before the error is kept
+        let (requests, error) = script.run_command("notify_and_fail", root, None, &[]);
+        assert_eq!(requests, vec![Request::Notify("before".to_string())]);
+        assert!(error.is_some());
+    }
+}
@@ -0,0 +1,247 @@
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+
+use crate::finder;
+use crate::model::details;
+
+// enough to tell text from binary files
+const PREFIX_LENGTH: u64 = 8192;
+// stop collecting at some point when searching for something very common
+const MAX_HITS: usize = 100_000;
+// long lines, such as minified code, are cut
+const MAX_LINE_LENGTH: usize = 500;
+// only the beginning of longer lines is searched, so that a huge file without line
+// breaks is not read into memory at once
+const MAX_LINE_BYTES: u64 = 64 * 1024;
+
+/// A line matching the search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    /// Relative to the directory the search started in.
+    pub path: PathBuf,
+    /// Starting at 1.
+    pub line_number: usize,
+    pub line: String,
+}
+
+/// Builds the expression to search for, ignoring the case unless it contains an
+/// uppercase letter.
+pub fn build_regex(pattern: &str) -> Result<regex::Regex, String> {
+    regex::RegexBuilder::new(pattern)
+        .case_insensitive(!pattern.chars().any(char::is_uppercase))
+        .build()
+        .map_err(|error| crate::model::filter::regex_error(&error))
+}
+
+fn is_text(path: &Path) -> std::io::Result<bool> {
+    let mut buffer = Vec::new();
+    std::fs::File::open(path)?
+        .take(PREFIX_LENGTH)
+        .read_to_end(&mut buffer)?;
+    Ok(match infer::get(&buffer) {
+        Some(kind) => kind.matcher_type() == infer::MatcherType::TEXT,
+        None => details::is_text(&buffer),
+    })
+}
+
+/// Sends the matching lines of a text file, returns `false` once the search should stop.
+fn search_file(
+    root: &Path,
+    path: &Path,
+    regex: &regex::Regex,
+    sender: &Sender<Hit>,
+    cancelled: &AtomicBool,
+) -> bool {
+    if !is_text(path).unwrap_or(false) {
+        return true;
+    }
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(_) => return true,
+    };
+    let relative = path.strip_prefix(root).unwrap_or(path);
+
+    let mut reader = BufReader::new(file);
+    let mut buffer = Vec::new();
+    let mut line_number = 0;
+    loop {
+        buffer.clear();
+        match (&mut reader)
+            .take(MAX_LINE_BYTES)
+            .read_until(b'\n', &mut buffer)
+        {
+            Ok(0) | Err(_) => return true,
+            Ok(_) => line_number += 1,
+        }
+        if buffer.last() != Some(&b'\n') && reader.skip_until(b'\n').is_err() {
+            return true;
+        }
+        if cancelled.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        let line = String::from_utf8_lossy(&buffer);
+        let line = line.trim_end_matches(['\n', '\r']);
+        if regex.is_match(line) {
+            let hit = Hit {
+                path: relative.to_path_buf(),
+                line_number,
+                line: line.chars().take(MAX_LINE_LENGTH).collect(),
+            };
+            if sender.send(hit).is_err() {
+                return false;
+            }
+        }
+    }
+}
+
+/// Searches the contents of the text files below a directory in a background thread,
+/// skipping binary files. Dropping it stops the search.
+pub struct Search {
+    pub regex: regex::Regex,
+    receiver: Receiver<Hit>,
+    cancelled: Arc<AtomicBool>,
+    files_searched: Arc<AtomicUsize>,
+    hits: usize,
+    done: bool,
+}
+
+impl Search {
+    pub fn start(root: &Path, regex: regex::Regex, show_hidden: bool) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let files_searched = Arc::new(AtomicUsize::new(0));
+
+        let walker = finder::walker(root, show_hidden);
+        let root = root.to_path_buf();
+        let thread_regex = regex.clone();
+        let thread_cancelled = cancelled.clone();
+        let thread_files_searched = files_searched.clone();
+        std::thread::spawn(move || {
+            for entry in walker.filter_map(Result::ok) {
+                if thread_cancelled.load(Ordering::Relaxed) {
+                    return;
+                }
+                if !entry
+                    .file_type()
+                    .is_some_and(|file_type| file_type.is_file())
+                {
+                    continue;
+                }
+                if !search_file(
+                    &root,
+                    entry.path(),
+                    &thread_regex,
+                    &sender,
+                    &thread_cancelled,
+                ) {
+                    return;
+                }
+                thread_files_searched.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+
+        Search {
+            regex,
+            receiver,
+            cancelled,
+            files_searched,
+            hits: 0,
+            done: false,
+        }
+    }
+
+    /// Hits found since the last call.
+    pub fn poll(&mut self) -> Vec<Hit> {
+        let mut hits = Vec::new();
+        while !self.done {
+            match self.receiver.try_recv() {
+                Ok(hit) => hits.push(hit),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => self.done = true,
+            }
+            if self.hits + hits.len() >= MAX_HITS {
+                self.cancel();
+                self.done = true;
+            }
+        }
+        self.hits += hits.len();
+        hits
+    }
+
+    pub fn cancel(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    pub fn files_searched(&self) -> usize {
+        self.files_searched.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Search {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_text_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(
+            root.join("src/main.rs"),
+            "fn main() {\n    Todo();\n}\n// todo\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("notes"), "nothing to see\r\ntodo: more\r\n").unwrap();
+        // a PNG signature followed by a matching line
+        std::fs::write(root.join("image.png"), b"\x89PNG\r\n\x1a\ntodo\n").unwrap();
+        std::fs::write(root.join("data"), b"todo\0\x01\x02").unwrap();
+        let long_line = "x".repeat(MAX_LINE_BYTES as usize);
+        std::fs::write(root.join("long"), format!("{long_line}todo\ntodo\n")).unwrap();
+
+        let mut search = Search::start(root, build_regex("todo").unwrap(), false);
+        let mut hits = Vec::new();
+        while !search.is_done() {
+            hits.extend(search.poll());
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        hits.sort_by(|a, b| (&a.path, a.line_number).cmp(&(&b.path, b.line_number)));
+
+        let hit = |path: &str, line_number, line: &str| Hit {
+            path: PathBuf::from(path),
+            line_number,
+            line: line.to_string(),
+        };
+        assert_eq!(
+            hits,
+            vec![
+                hit("long", 2, "todo"),
+                hit("notes", 2, "todo: more"),
+                hit("src/main.rs", 2, "    Todo();"),
+                hit("src/main.rs", 4, "// todo"),
+            ]
+        );
+        assert_eq!(search.files_searched(), 5);
+
+        assert!(build_regex("Todo").unwrap().is_match("Todo"));
+        assert!(!build_regex("Todo").unwrap().is_match("todo"));
+        assert!(build_regex("(").is_err());
+    }
+}
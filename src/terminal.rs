@@ -0,0 +1,44 @@
+use std::io;
+use std::path::Path;
+use std::process::ExitStatus;
+
+use crossterm::{cursor, execute, terminal};
+
+/// Gives the terminal back to the shell, e.g. to run a child process in it.
+pub fn suspend() -> io::Result<()> {
+    execute!(io::stdout(), terminal::LeaveAlternateScreen, cursor::Show)?;
+    terminal::disable_raw_mode()
+}
+
+/// Takes the terminal over again after [`suspend`]. The screen has to be redrawn
+/// from scratch afterwards.
+pub fn resume() -> io::Result<()> {
+    terminal::enable_raw_mode()?;
+    execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)
+}
+
+/// Runs `f` with the terminal suspended, resuming it even if `f` fails.
+pub fn suspended<T>(f: impl FnOnce() -> T) -> io::Result<T> {
+    suspend()?;
+    let result = f();
+    resume()?;
+    Ok(result)
+}
+
+/// Opens `path` in `$VISUAL`, `$EDITOR` or vi, at `line` if given, and waits for it.
+pub fn edit(path: &Path, line: Option<usize>) -> io::Result<ExitStatus> {
+    let mut command = std::process::Command::new("sh");
+    command.arg("-c");
+    match line {
+        Some(line) => command
+            .arg(r#"${VISUAL:-${EDITOR:-vi}} +"$2" "$1""#)
+            .arg("sh")
+            .arg(path)
+            .arg(line.to_string()),
+        None => command
+            .arg(r#"${VISUAL:-${EDITOR:-vi}} "$1""#)
+            .arg("sh")
+            .arg(path),
+    };
+    suspended(|| command.status()).and_then(|status| status)
+}
@@ -0,0 +1,457 @@
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::{Path, PathBuf};
+
+use crate::path_codec::{percent_decode, percent_encode};
+
+const INFO_EXTENSION: &str = ".trashinfo";
+const INFO_HEADER: &str = "[Trash Info]";
+const DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+const STICKY_BIT: u32 = 0o1000;
+
+fn create_private_dir(path: &Path) -> io::Result<()> {
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(path)
+}
+
+/// Lines of `/proc/self/mounts` escape spaces and such as octal numbers, e.g. `\040`.
+fn unescape_mount_point(escaped: &str) -> PathBuf {
+    let bytes = escaped.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let octal = bytes
+            .get(i + 1..i + 4)
+            .and_then(|octal| std::str::from_utf8(octal).ok())
+            .and_then(|octal| u8::from_str_radix(octal, 8).ok());
+        match (bytes[i], octal) {
+            (b'\\', Some(byte)) => {
+                unescaped.push(byte);
+                i += 4;
+            }
+            (byte, _) => {
+                unescaped.push(byte);
+                i += 1;
+            }
+        }
+    }
+    PathBuf::from(OsString::from_vec(unescaped))
+}
+
+fn mount_points() -> Vec<PathBuf> {
+    fs::read_to_string("/proc/self/mounts")
+        .map(|mounts| {
+            mounts
+                .lines()
+                .filter_map(|line| line.split_whitespace().nth(1))
+                .map(unescape_mount_point)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The top directory of the file system `path` is on.
+fn mount_top(path: &Path) -> io::Result<PathBuf> {
+    let device = fs::metadata(path)?.dev();
+    let mut top = path;
+    while let Some(parent) = top.parent() {
+        if fs::metadata(parent)?.dev() != device {
+            break;
+        }
+        top = parent;
+    }
+    Ok(top.to_path_buf())
+}
+
+/// A trash directory as described by the freedesktop.org Trash specification: trashed
+/// entries are moved into `files`, and `info/<name>.trashinfo` records where they came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrashDir {
+    pub path: PathBuf,
+    // original paths are stored relative to the top directory in per-mount trash directories
+    top_dir: Option<PathBuf>,
+}
+
+impl TrashDir {
+    /// `$XDG_DATA_HOME/Trash`, which takes entries from the file system it is on.
+    pub fn home() -> Option<Self> {
+        let data_home = match std::env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+        };
+        Some(TrashDir {
+            path: data_home.join("Trash"),
+            top_dir: None,
+        })
+    }
+
+    /// `$top/.Trash/$uid` if the administrator prepared `$top/.Trash`, `$top/.Trash-$uid`
+    /// otherwise. `None` if neither exists and `create` is false.
+    fn for_mount(top_dir: &Path, create: bool) -> Option<Self> {
+        let uid = users::get_current_uid();
+
+        // the shared directory has to be a real directory with the sticky bit set
+        let shared = top_dir.join(".Trash");
+        let shared_is_valid = fs::symlink_metadata(&shared)
+            .is_ok_and(|metadata| metadata.is_dir() && metadata.mode() & STICKY_BIT != 0);
+
+        let candidates = if shared_is_valid {
+            vec![
+                shared.join(uid.to_string()),
+                top_dir.join(format!(".Trash-{uid}")),
+            ]
+        } else {
+            vec![top_dir.join(format!(".Trash-{uid}"))]
+        };
+        let path = match candidates.iter().find(|path| path.is_dir()) {
+            Some(path) => path.clone(),
+            None if create => candidates
+                .into_iter()
+                .find(|path| create_private_dir(path).is_ok())?,
+            None => return None,
+        };
+
+        Some(TrashDir {
+            path,
+            top_dir: Some(top_dir.to_path_buf()),
+        })
+    }
+
+    /// Where `path` goes when trashed: the home trash if it is on the same file system,
+    /// the trash directory of its mount otherwise.
+    pub fn for_path(path: &Path) -> io::Result<Self> {
+        let parent = path
+            .parent()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "cannot trash '/'"))?;
+        let device = fs::metadata(parent)?.dev();
+
+        if let Some(home) = TrashDir::home() {
+            let existing = home.path.ancestors().find(|dir| dir.exists());
+            if existing.is_some_and(|dir| fs::metadata(dir).is_ok_and(|m| m.dev() == device)) {
+                return Ok(home);
+            }
+        }
+
+        TrashDir::for_mount(&mount_top(parent)?, true).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("no trash directory for '{}'", parent.display()),
+            )
+        })
+    }
+
+    /// The home trash and the trash directories of all mounted file systems that have one.
+    pub fn all() -> Vec<Self> {
+        let mut dirs: Vec<_> = TrashDir::home().into_iter().collect();
+        for top_dir in mount_points() {
+            if let Some(dir) = TrashDir::for_mount(&top_dir, false) {
+                if !dirs.contains(&dir) {
+                    dirs.push(dir);
+                }
+            }
+        }
+        dirs
+    }
+
+    fn files(&self) -> PathBuf {
+        self.path.join("files")
+    }
+
+    fn info(&self) -> PathBuf {
+        self.path.join("info")
+    }
+
+    /// Moves `path` into the trash, returns its new location.
+    pub fn put(&self, path: &Path) -> io::Result<PathBuf> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+        create_private_dir(&self.files())?;
+        create_private_dir(&self.info())?;
+
+        let original = match &self.top_dir {
+            Some(top_dir) => path.strip_prefix(top_dir).unwrap_or(path),
+            None => path,
+        };
+        let contents = format!(
+            "{INFO_HEADER}\nPath={}\nDeletionDate={}\n",
+            percent_encode(original),
+            chrono::Local::now().format(DATE_FORMAT)
+        );
+
+        // creating the info file reserves the name, see the specification
+        let (trash_name, info_path) = (1..)
+            .map(|n| {
+                let mut trash_name = name.to_os_string();
+                if n > 1 {
+                    trash_name.push(format!(".{n}"));
+                }
+                let mut info_name = trash_name.clone();
+                info_name.push(INFO_EXTENSION);
+                (trash_name, self.info().join(info_name))
+            })
+            .find_map(|(trash_name, info_path)| {
+                match fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&info_path)
+                {
+                    Ok(mut file) => Some(
+                        file.write_all(contents.as_bytes())
+                            .map(|()| (trash_name, info_path)),
+                    ),
+                    Err(error) if error.kind() == io::ErrorKind::AlreadyExists => None,
+                    Err(error) => Some(Err(error)),
+                }
+            })
+            .unwrap()?;
+
+        let destination = self.files().join(trash_name);
+        if let Err(error) = fs::rename(path, &destination) {
+            let _ = fs::remove_file(info_path);
+            return Err(error);
+        }
+        Ok(destination)
+    }
+
+    fn read_info(&self, info_path: &Path) -> Option<TrashedItem> {
+        let contents = fs::read_to_string(info_path).ok()?;
+        let mut lines = contents.lines();
+        if lines.next()?.trim() != INFO_HEADER {
+            return None;
+        }
+
+        let mut original_path = None;
+        let mut deletion_date = None;
+        for line in lines {
+            match line.split_once('=') {
+                Some(("Path", value)) => original_path = Some(percent_decode(value.trim())),
+                Some(("DeletionDate", value)) => {
+                    deletion_date =
+                        chrono::NaiveDateTime::parse_from_str(value.trim(), DATE_FORMAT).ok()
+                }
+                _ => {}
+            }
+        }
+
+        let original_path = match (&self.top_dir, original_path?) {
+            (Some(top_dir), path) if path.is_relative() => top_dir.join(path),
+            (_, path) => path,
+        };
+        // the name is kept as bytes, like the original path
+        let name = info_path
+            .file_name()?
+            .as_bytes()
+            .strip_suffix(INFO_EXTENSION.as_bytes())?;
+        let name = OsStr::from_bytes(name).to_os_string();
+
+        Some(TrashedItem {
+            name,
+            original_path,
+            deletion_date,
+            trash_dir: self.clone(),
+        })
+    }
+
+    /// Entries of this trash directory, skipping ones with broken metadata.
+    pub fn items(&self) -> Vec<TrashedItem> {
+        fs::read_dir(self.info())
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .filter_map(|entry| self.read_info(&entry.path()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Moves `path` into the trash directory for it, returns its new location.
+pub fn put(path: &Path) -> io::Result<PathBuf> {
+    TrashDir::for_path(path)?.put(path)
+}
+
+/// Entries of all trash directories, most recently deleted first.
+pub fn items() -> Vec<TrashedItem> {
+    let mut items: Vec<_> = TrashDir::all().iter().flat_map(TrashDir::items).collect();
+    items.sort_by_key(|item| std::cmp::Reverse(item.deletion_date));
+    items
+}
+
+/// Moves the entry stored at `path` inside a trash directory to `destination` and drops
+/// its metadata, refusing to replace anything.
+pub fn take_out(path: &Path, destination: &Path) -> io::Result<()> {
+    let (trash_dir, name) = match (path.parent().and_then(Path::parent), path.file_name()) {
+        (Some(trash_dir), Some(name)) => (trash_dir, name),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("'{}' is not in the trash", path.display()),
+            ))
+        }
+    };
+    if fs::symlink_metadata(destination).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("'{}' already exists", destination.display()),
+        ));
+    }
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut info_name = name.to_os_string();
+    info_name.push(INFO_EXTENSION);
+    fs::rename(path, destination)?;
+    fs::remove_file(trash_dir.join("info").join(info_name))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrashedItem {
+    /// Name within the trash directory, which may differ from the original one.
+    pub name: OsString,
+    pub original_path: PathBuf,
+    pub deletion_date: Option<chrono::NaiveDateTime>,
+    pub trash_dir: TrashDir,
+}
+
+impl TrashedItem {
+    /// Where the entry is stored in the trash.
+    pub fn path(&self) -> PathBuf {
+        self.trash_dir.files().join(&self.name)
+    }
+
+    fn info_path(&self) -> PathBuf {
+        let mut info_name = self.name.clone();
+        info_name.push(INFO_EXTENSION);
+        self.trash_dir.info().join(info_name)
+    }
+
+    /// Moves the entry back to where it was deleted from, refusing to replace anything.
+    pub fn restore(&self) -> io::Result<()> {
+        take_out(&self.path(), &self.original_path)
+    }
+
+    /// Deletes the entry permanently.
+    pub fn purge(&self) -> io::Result<()> {
+        let path = self.path();
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(&path)?,
+            Ok(_) => fs::remove_file(&path)?,
+            // only the metadata is left
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error),
+        }
+        fs::remove_file(self.info_path())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mount_points() {
+        assert_eq!(
+            unescape_mount_point("/mnt/my\\040disk"),
+            Path::new("/mnt/my disk")
+        );
+    }
+
+    #[test]
+    fn put_restore_and_purge() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("dir/nested")).unwrap();
+        fs::write(root.join("file"), "first").unwrap();
+
+        let trash_dir = TrashDir {
+            path: root.join("Trash"),
+            top_dir: None,
+        };
+
+        let trashed = trash_dir.put(&root.join("file")).unwrap();
+        assert_eq!(trashed, root.join("Trash/files/file"));
+        fs::write(root.join("file"), "second").unwrap();
+        assert_eq!(
+            trash_dir.put(&root.join("file")).unwrap(),
+            root.join("Trash/files/file.2")
+        );
+        trash_dir.put(&root.join("dir")).unwrap();
+        assert!(!root.join("dir").exists());
+
+        let info = fs::read_to_string(root.join("Trash/info/file.trashinfo")).unwrap();
+        assert!(info.starts_with(&format!(
+            "[Trash Info]\nPath={}\nDeletionDate=",
+            percent_encode(&root.join("file"))
+        )));
+
+        let mut items = trash_dir.items();
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+        let names: Vec<_> = items
+            .iter()
+            .map(|item| item.name.to_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["dir", "file", "file.2"]);
+        assert_eq!(items[1].original_path, root.join("file"));
+        assert!(items[1].deletion_date.is_some());
+
+        // restoring never replaces anything
+        fs::write(root.join("file"), "third").unwrap();
+        assert!(items[1].restore().is_err());
+        fs::remove_file(root.join("file")).unwrap();
+
+        items[2].purge().unwrap();
+        items[1].restore().unwrap();
+        assert_eq!(fs::read_to_string(root.join("file")).unwrap(), "first");
+        items[0].restore().unwrap();
+        assert!(root.join("dir/nested").is_dir());
+        assert!(trash_dir.items().is_empty());
+    }
+
+    #[test]
+    fn relative_paths_in_mount_trash() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("data")).unwrap();
+        fs::write(root.join("data/file"), "").unwrap();
+
+        let trash_dir = TrashDir::for_mount(root, true).unwrap();
+        assert_eq!(
+            trash_dir.path,
+            root.join(format!(".Trash-{}", users::get_current_uid()))
+        );
+        trash_dir.put(&root.join("data/file")).unwrap();
+
+        let info = fs::read_to_string(trash_dir.info().join("file.trashinfo")).unwrap();
+        assert!(info.contains("\nPath=data/file\n"));
+        assert_eq!(trash_dir.items()[0].original_path, root.join("data/file"));
+    }
+
+    #[test]
+    fn names_not_in_utf8() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let path = root.join(OsStr::from_bytes(b"bad\xff"));
+        fs::write(&path, "").unwrap();
+
+        let trash_dir = TrashDir {
+            path: root.join("Trash"),
+            top_dir: None,
+        };
+        trash_dir.put(&path).unwrap();
+
+        let items = trash_dir.items();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, OsStr::from_bytes(b"bad\xff"));
+        assert_eq!(items[0].original_path, path);
+        items[0].restore().unwrap();
+        assert!(path.exists());
+    }
+}
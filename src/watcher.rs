@@ -0,0 +1,229 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use inotify::{Inotify, WatchDescriptor, WatchMask};
+
+// changes are reported once no event has come for this long
+const DEBOUNCE: Duration = Duration::from_millis(200);
+// but not later than this after the first one, a download keeps writing for a while
+const MAX_DELAY: Duration = Duration::from_secs(1);
+const BUFFER_SIZE: usize = 4096;
+
+fn watch_mask() -> WatchMask {
+    WatchMask::CREATE
+        | WatchMask::DELETE
+        | WatchMask::MOVE
+        | WatchMask::MODIFY
+        | WatchMask::ATTRIB
+        | WatchMask::CLOSE_WRITE
+        | WatchMask::DELETE_SELF
+        | WatchMask::MOVE_SELF
+}
+
+/// A watched path and the changes to it that have not been reported yet.
+struct Watch {
+    path: PathBuf,
+    descriptor: Option<WatchDescriptor>,
+    // when the first and the last unreported events came
+    pending: Option<(Instant, Instant)>,
+}
+
+impl Watch {
+    fn record(&mut self, now: Instant) {
+        let first = self.pending.map_or(now, |(first, _)| first);
+        self.pending = Some((first, now));
+    }
+
+    /// Whether the pending changes have settled, forgetting them if so.
+    fn settled(&mut self, now: Instant) -> bool {
+        match self.pending {
+            Some((first, last))
+                if now.duration_since(last) >= DEBOUNCE
+                    || now.duration_since(first) >= MAX_DELAY =>
+            {
+                self.pending = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Stops watching unless one of `others` watches the same inode, which shares the
+/// descriptor.
+fn unwatch<'a>(
+    inotify: &Inotify,
+    watch: Option<Watch>,
+    mut others: impl Iterator<Item = &'a Watch>,
+) {
+    let descriptor = match watch.and_then(|watch| watch.descriptor) {
+        Some(descriptor) => descriptor,
+        None => return,
+    };
+    if !others.any(|other| other.descriptor.as_ref() == Some(&descriptor)) {
+        let _ = inotify.watches().remove(descriptor);
+    }
+}
+
+/// What changed since the last report.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Changes {
+    pub directory: bool,
+    pub preview: bool,
+}
+
+/// Watches the listed directories and the previewed entry with inotify, reporting
+/// changes once they settle so that a burst of events causes a single refresh.
+pub struct Watcher {
+    inotify: Inotify,
+    buffer: Vec<u8>,
+    directories: Vec<Watch>,
+    preview: Option<Watch>,
+}
+
+impl Watcher {
+    pub fn new() -> io::Result<Self> {
+        Ok(Watcher {
+            inotify: Inotify::init()?,
+            buffer: vec![0; BUFFER_SIZE],
+            directories: Vec::new(),
+            preview: None,
+        })
+    }
+
+    /// Watches `dirs` instead of the directories watched so far: the listed one and, in
+    /// a tree, the expanded ones below it.
+    pub fn watch_directories(&mut self, dirs: &[PathBuf]) {
+        if self.directories.iter().map(|watch| &watch.path).eq(dirs) {
+            return;
+        }
+        let mut previous = std::mem::take(&mut self.directories);
+        for dir in dirs {
+            // directories still listed keep their pending changes
+            let watch = match previous.iter().position(|watch| &watch.path == dir) {
+                Some(index) => previous.swap_remove(index),
+                None => self.watch(dir),
+            };
+            self.directories.push(watch);
+        }
+        for watch in previous {
+            let others = self.directories.iter().chain(&self.preview);
+            unwatch(&self.inotify, Some(watch), others);
+        }
+    }
+
+    /// Watches the entry being previewed, if any, instead of the one watched so far.
+    pub fn watch_preview(&mut self, path: Option<&Path>) {
+        if self.preview.as_ref().map(|watch| watch.path.as_path()) == path {
+            return;
+        }
+        let previous = self.preview.take();
+        unwatch(&self.inotify, previous, self.directories.iter());
+        self.preview = path.map(|path| self.watch(path));
+    }
+
+    fn watch(&mut self, path: &Path) -> Watch {
+        Watch {
+            path: path.to_path_buf(),
+            // what cannot be watched, e.g. for lack of permission, is not refreshed
+            descriptor: self.inotify.watches().add(path, watch_mask()).ok(),
+            pending: None,
+        }
+    }
+
+    /// Reads the events that came since the last call, and reports what changed.
+    pub fn poll(&mut self) -> Changes {
+        let now = Instant::now();
+        // reads fail with `WouldBlock` once there is nothing left
+        while let Ok(events) = self.inotify.read_events(&mut self.buffer) {
+            for event in events {
+                for watch in self.directories.iter_mut().chain(&mut self.preview) {
+                    if watch.descriptor.as_ref() == Some(&event.wd) {
+                        watch.record(now);
+                    }
+                }
+            }
+        }
+
+        // every directory is checked, so that none keeps stale changes
+        let mut directory = false;
+        for watch in &mut self.directories {
+            directory |= watch.settled(now);
+        }
+        Changes {
+            directory,
+            preview: self
+                .preview
+                .as_mut()
+                .is_some_and(|watch| watch.settled(now)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait_for_changes(watcher: &mut Watcher) -> Changes {
+        let start = Instant::now();
+        loop {
+            let changes = watcher.poll();
+            if changes != Changes::default() || start.elapsed() > 2 * MAX_DELAY {
+                return changes;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn debounced_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("previewed"), "").unwrap();
+
+        let mut watcher = Watcher::new().unwrap();
+        watcher.watch_directories(&[root.to_path_buf()]);
+        watcher.watch_preview(Some(&root.join("previewed")));
+        assert_eq!(watcher.poll(), Changes::default());
+
+        for index in 0..10 {
+            std::fs::write(root.join(format!("file{index}")), "").unwrap();
+        }
+        // reported once, after the burst
+        assert!(!watcher.poll().directory);
+        assert_eq!(
+            wait_for_changes(&mut watcher),
+            Changes {
+                directory: true,
+                preview: false
+            }
+        );
+        assert_eq!(watcher.poll(), Changes::default());
+
+        std::fs::write(root.join("previewed"), "changed").unwrap();
+        assert!(wait_for_changes(&mut watcher).preview);
+
+        // the previous preview is not watched anymore
+        watcher.watch_preview(None);
+        std::fs::write(root.join("previewed"), "again").unwrap();
+        assert!(!wait_for_changes(&mut watcher).preview);
+    }
+
+    #[test]
+    fn expanded_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join("expanded")).unwrap();
+
+        let mut watcher = Watcher::new().unwrap();
+        watcher.watch_directories(&[root.to_path_buf(), root.join("expanded")]);
+        std::fs::write(root.join("expanded/file"), "").unwrap();
+        assert!(wait_for_changes(&mut watcher).directory);
+
+        // collapsed again
+        watcher.watch_directories(&[root.to_path_buf()]);
+        std::fs::write(root.join("expanded/other"), "").unwrap();
+        assert!(!wait_for_changes(&mut watcher).directory);
+    }
+}
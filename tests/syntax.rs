@@ -1,6 +1,9 @@
 use rsfm::column;
+use rsfm::layout::Layout;
 use rsfm::parser::CheckResult;
+use rsfm::sort;
 use rsfm::ViewOptions;
+use rsfm::{ConfigError, ConfigWarning};
 
 fn parse_syntax(source: &str) -> CheckResult {
     rlua::Lua::new().context(|ctx| -> CheckResult {
@@ -26,7 +29,7 @@ fn parse_values(source: &str) -> ViewOptions {
         ctx.load(source).exec().unwrap();
 
         let rsfm = globals.get::<_, rlua::Value>("rsfm").unwrap();
-        rsfm::parser::parse_values(rsfm).unwrap()
+        rsfm::parser::parse_values(rsfm).unwrap().0
     })
 }
 
@@ -50,7 +53,7 @@ fn correct_syntax() {
     }
     "#;
 
-    assert!(parse_syntax(&config).is_ok());
+    assert!(parse_syntax(config).is_ok());
 }
 
 #[test]
@@ -59,7 +62,7 @@ fn no_rsfm() {
     var = 4
     "#;
 
-    assert!(parse_syntax(&config).is_ok());
+    assert!(parse_syntax(config).is_ok());
 }
 
 #[test]
@@ -75,7 +78,7 @@ fn unexpected_type() {
     }
     "#;
 
-    let result = parse_syntax(&config);
+    let result = parse_syntax(config);
     assert!(result.is_err());
 
     let result = result.unwrap_err();
@@ -83,13 +86,16 @@ fn unexpected_type() {
     assert!(result
         .iter()
         .find(|&e| {
-            e.eq("Unexpected type 'table' for variable 'rsfm.show_hidden', use 'boolean'")
+            e.to_string()
+                .eq("Unexpected type 'table' for variable 'rsfm.show_hidden', use 'boolean'")
         })
         .is_some(),);
     assert!(result
         .iter()
         .find(|&e| {
-            e.eq("Unexpected type 'boolean' for variable 'rsfm.entry_format.1.type', use 'string'")
+            e.to_string().eq(
+                "Unexpected type 'boolean' for variable 'rsfm.entry_format.1.type', use 'string'",
+            )
         })
         .is_some(),);
 }
@@ -108,7 +114,7 @@ fn unknown_variable() {
     rsfm.var = true
     "#;
 
-    let result = parse_syntax(&config);
+    let result = parse_syntax(config);
     assert!(result.is_err());
 
     let result = result.unwrap_err();
@@ -116,29 +122,35 @@ fn unknown_variable() {
     assert_eq!(result.len(), 5);
     assert!(result
         .iter()
-        .find(|&e| { e.eq("Unknown variable 'rsfm.show_hixxxx'") })
+        .find(|&e| { e.to_string().eq("Unknown variable 'rsfm.show_hixxxx'") })
         .is_some(),);
     assert!(result
         .iter()
         .find(|&e| {
-            e.eq("Unknown variable 'rsfm.show_hidxxx'. Did you mean 'rsfm.show_hidden'?")
+            e.to_string()
+                .eq("Unknown variable 'rsfm.show_hidxxx'. Did you mean 'rsfm.show_hidden'?")
         })
         .is_some(),);
     assert!(result
         .iter()
         .find(|&e| {
-            e.eq("Unexpected type 'table' for variable 'rsfm.show_hidden', use 'boolean'")
+            e.to_string()
+                .eq("Unexpected type 'table' for variable 'rsfm.show_hidden', use 'boolean'")
         })
         .is_some(),);
     assert!(result
         .iter()
         .find(|&e| {
-            e.eq("Unknown variable 'rsfm.entry_format.x'. Did you mean 'rsfm.entry_format.{}'?")
+            e.to_string()
+                .eq("Unknown variable 'rsfm.entry_format.x'. Did you mean 'rsfm.entry_format.{}'?")
         })
         .is_some(),);
     assert!(result
         .iter()
-        .find(|&e| { e.eq("Unknown variable 'rsfm.var'") })
+        .find(|&e| {
+            e.to_string()
+                .eq("Unknown variable 'rsfm.var'. Did you mean 'rsfm.sort'?")
+        })
         .is_some(),);
 }
 
@@ -152,13 +164,13 @@ fn unknown_variable_in_table() {
     }
     "#;
 
-    let result = parse_syntax(&config);
+    let result = parse_syntax(config);
     assert!(result.is_err());
 
     let result = result.unwrap_err();
     assert_eq!(result.len(), 1);
     assert_eq!(
-        result[0],
+        result[0].to_string(),
         "Unknown variable 'rsfm.entry_format.{}.x'. Did you mean 'rsfm.entry_format.{}'?"
     );
 
@@ -170,13 +182,13 @@ fn unknown_variable_in_table() {
     }
     "#;
 
-    let result = parse_syntax(&config);
+    let result = parse_syntax(config);
     assert!(result.is_err());
 
     let result = result.unwrap_err();
     assert_eq!(result.len(), 1);
     assert_eq!(
-        result[0],
+        result[0].to_string(),
         "Unknown variable 'rsfm.entry_format.{}.hype'. Did you mean 'rsfm.entry_format.{}.type'?"
     );
 }
@@ -201,8 +213,8 @@ fn correct_values() {
     }
     "#;
 
-    let view_options = parse_values(&config);
-    assert_eq!(view_options.show_hidden, false);
+    let view_options = parse_values(config);
+    assert!(!view_options.show_hidden);
     assert_eq!(view_options.entry_format.len(), 2);
 
     let expected_0 = column::Column {
@@ -233,8 +245,8 @@ fn entry_format_incomplete() {
     }
     "#;
 
-    let view_options = parse_values(&config);
-    assert_eq!(view_options.show_hidden, false);
+    let view_options = parse_values(config);
+    assert!(!view_options.show_hidden);
     assert_eq!(view_options.entry_format.len(), 0);
 }
 
@@ -251,7 +263,274 @@ fn entry_format_wrong_type() {
     }
     "#;
 
-    let view_options = parse_values(&config);
-    assert_eq!(view_options.show_hidden, false);
+    let view_options = parse_values(config);
+    assert!(!view_options.show_hidden);
     assert_eq!(view_options.entry_format.len(), 0);
 }
+
+#[test]
+fn entry_format_warnings() {
+    let config = r#"
+    rsfm.entry_format = {
+        {
+            type = "name",
+            width = 5,
+            alignment = "left",
+        },
+        {
+            type = "size",
+            width = 5,
+            is_fixed_width = true,
+            alignment = "middle",
+        }
+    }
+    "#;
+
+    let warnings = rlua::Lua::new().context(|ctx| {
+        let rsfm = ctx.create_table().unwrap();
+        ctx.globals().set("rsfm", rsfm).unwrap();
+        ctx.load(config).exec().unwrap();
+
+        let rsfm = ctx.globals().get::<_, rlua::Value>("rsfm").unwrap();
+        rsfm::parser::parse_values(rsfm).unwrap().1
+    });
+
+    assert_eq!(
+        warnings,
+        vec![
+            ConfigWarning(ConfigError::MissingField {
+                name: "rsfm.entry_format.1.is_fixed_width".to_string()
+            }),
+            ConfigWarning(ConfigError::InvalidValue {
+                name: "rsfm.entry_format.2.alignment".to_string(),
+                value: "middle".to_string()
+            }),
+        ]
+    );
+}
+
+#[test]
+fn sort_values() {
+    let config = r#"
+    rsfm.sort = {
+        by = "mtime",
+        reverse = true,
+        dirs_first = false,
+        natural = false,
+        case_sensitive = false,
+        follow_links = true,
+    }
+    "#;
+
+    assert!(parse_syntax(config).is_ok());
+
+    let view_options = parse_values(config);
+    let expected = sort::SortOptions {
+        by: sort::SortKey::Modified,
+        reverse: true,
+        dirs_first: false,
+        natural: false,
+        case_sensitive: false,
+        follow_links: true,
+    };
+    assert_eq!(view_options.sort, expected);
+
+    let view_options = parse_values("rsfm.sort = { by = \"size\" }");
+    assert_eq!(view_options.sort.by, sort::SortKey::Size);
+    assert!(view_options.sort.dirs_first);
+}
+
+#[test]
+fn column_options() {
+    let config = r#"
+    rsfm.entry_format = {
+        { type = "modified", width = 16, is_fixed_width = true, alignment = "left", format = "%d.%m.%Y" },
+        { type = "owner", width = 8, is_fixed_width = true, alignment = "left", numeric = true },
+        { type = "group", width = 8, is_fixed_width = true, alignment = "left" },
+        { type = "MimeType", width = 1, is_fixed_width = false, alignment = "left" },
+    }
+    "#;
+
+    assert!(parse_syntax(config).is_ok());
+
+    let view_options = parse_values(config);
+    let column_types: Vec<_> = view_options
+        .entry_format
+        .into_iter()
+        .map(|column| column.column_type)
+        .collect();
+    assert_eq!(
+        column_types,
+        vec![
+            column::ColumnType::Modified {
+                format: "%d.%m.%Y".to_string()
+            },
+            column::ColumnType::Owner { numeric: true },
+            column::ColumnType::Group { numeric: false },
+            column::ColumnType::MimeType,
+        ]
+    );
+}
+
+#[test]
+fn keys() {
+    use rsfm::keys::{parse_sequence, Action, Lookup};
+
+    let config = r#"
+    rsfm.keys = {
+        ["<C-n>"] = "move_down",
+        ["zz"] = "toggle_hidden",
+        ["q"] = "none",
+        ["x"] = "explode",
+        ["<Bogus>"] = "quit",
+    }
+    "#;
+
+    assert!(parse_syntax(config).is_ok());
+    assert!(parse_syntax("rsfm.keys = { j = 1 }").is_err());
+
+    let (view_options, warnings) = rlua::Lua::new().context(|ctx| {
+        let rsfm = ctx.create_table().unwrap();
+        ctx.globals().set("rsfm", rsfm).unwrap();
+        ctx.load(config).exec().unwrap();
+
+        let rsfm = ctx.globals().get::<_, rlua::Value>("rsfm").unwrap();
+        rsfm::parser::parse_values(rsfm).unwrap()
+    });
+
+    let lookup = |notation: &str| view_options.keys.lookup(&parse_sequence(notation).unwrap());
+    assert!(matches!(lookup("<C-n>"), Lookup::Action(Action::MoveDown)));
+    assert!(matches!(lookup("z"), Lookup::Pending));
+    assert!(matches!(lookup("zz"), Lookup::Action(Action::ToggleHidden)));
+    assert!(matches!(lookup("q"), Lookup::None));
+    assert!(matches!(lookup("j"), Lookup::Action(Action::MoveDown)));
+
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings.contains(&ConfigWarning(ConfigError::InvalidValue {
+        name: "rsfm.keys.x".to_string(),
+        value: "explode".to_string()
+    })));
+    assert!(warnings.contains(&ConfigWarning(ConfigError::InvalidValue {
+        name: "rsfm.keys".to_string(),
+        value: "<Bogus>".to_string()
+    })));
+}
+
+#[test]
+fn commands() {
+    use rsfm::keys::{parse_sequence, Lookup};
+
+    let config = r#"
+    rsfm.commands = {
+        greet = function() end,
+    }
+    rsfm.keys = {
+        ["<Space>g"] = "greet",
+        ["x"] = "missing",
+    }
+    "#;
+
+    assert!(parse_syntax(config).is_ok());
+    assert!(parse_syntax("rsfm.commands = { greet = 'hello' }").is_err());
+
+    let (view_options, warnings) = rlua::Lua::new().context(|ctx| {
+        let rsfm = ctx.create_table().unwrap();
+        ctx.globals().set("rsfm", rsfm).unwrap();
+        ctx.load(config).exec().unwrap();
+
+        let rsfm = ctx.globals().get::<_, rlua::Value>("rsfm").unwrap();
+        rsfm::parser::parse_values(rsfm).unwrap()
+    });
+
+    assert!(matches!(
+        view_options.keys.lookup(&parse_sequence("<Space>g").unwrap()),
+        Lookup::Command(name) if name == "greet"
+    ));
+    assert_eq!(
+        warnings,
+        vec![ConfigWarning(ConfigError::InvalidValue {
+            name: "rsfm.keys.x".to_string(),
+            value: "missing".to_string()
+        })]
+    );
+}
+
+#[test]
+fn journal_values() {
+    let config = "rsfm.journal = { persist = true, size = 20 }";
+
+    assert!(parse_syntax(config).is_ok());
+
+    let view_options = parse_values(config);
+    assert!(view_options.journal.persist);
+    assert_eq!(view_options.journal.size, 20);
+
+    let view_options = parse_values("rsfm.journal = { size = 0 }");
+    assert!(!view_options.journal.persist);
+    assert_eq!(view_options.journal.size, 100);
+}
+
+#[test]
+fn bookmarks() {
+    let config = r#"rsfm.bookmarks = { d = "~/Downloads", ["/"] = "/" }"#;
+
+    assert!(parse_syntax(config).is_ok());
+    assert!(parse_syntax("rsfm.bookmarks = { d = 1 }").is_err());
+
+    let view_options = parse_values(config);
+    assert_eq!(
+        view_options.bookmarks.get(&'d').map(String::as_str),
+        Some("~/Downloads")
+    );
+    assert_eq!(
+        view_options.bookmarks.get(&'/').map(String::as_str),
+        Some("/")
+    );
+
+    // bookmarks are opened by a single key
+    let view_options = parse_values(r#"rsfm.bookmarks = { dl = "~/Downloads", x = "" }"#);
+    assert!(view_options.bookmarks.is_empty());
+}
+
+#[test]
+fn layout() {
+    assert!(parse_syntax(r#"rsfm.layout = "dual""#).is_ok());
+    assert!(parse_syntax("rsfm.layout = 2").is_err());
+
+    assert_eq!(parse_values(r#"rsfm.layout = "Dual""#).layout, Layout::Dual);
+    assert_eq!(
+        parse_values(r#"rsfm.layout = "quad""#).layout,
+        Layout::Single
+    );
+    assert_eq!(parse_values("").layout, Layout::Single);
+}
+
+#[test]
+fn miller_columns() {
+    let config = r#"
+    rsfm.layout = "miller"
+    rsfm.miller = {
+        parent = { width = 20, is_fixed_width = true },
+        preview = { width = 2 },
+    }
+    "#;
+
+    assert!(parse_syntax(config).is_ok());
+    assert!(parse_syntax("rsfm.miller = { child = { width = 1 } }").is_err());
+
+    let view_options = parse_values(config);
+    assert_eq!(view_options.layout, Layout::Miller);
+    let miller = view_options.miller;
+    assert_eq!(
+        (miller.parent.width, miller.parent.is_fixed_width),
+        (20, true)
+    );
+    assert_eq!(
+        (miller.current.width, miller.current.is_fixed_width),
+        (3, false)
+    );
+    assert_eq!(
+        (miller.preview.width, miller.preview.is_fixed_width),
+        (2, false)
+    );
+}